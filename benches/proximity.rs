@@ -0,0 +1,89 @@
+// Proximity checks through the spatial grid against scanning every object:
+//
+//     cargo bench --bench proximity
+//
+// Objects are spread at a constant density, so a bigger level is a wider one,
+// as it would be in the game.
+
+use bevy::math::Vec2;
+use mistery::spatial::SpatialGrid;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// same as NPCs in the game
+const RADIUS: f32 = 175.;
+const CELL_SIZE: f32 = 200.;
+// area per object
+const SPACING: f32 = 100.;
+
+fn level(rng: &fastrand::Rng, objects: usize) -> Vec<(u32, Vec2)> {
+    let side = (objects as f32).sqrt() * SPACING;
+    (0..objects as u32)
+        .map(|key| (key, Vec2::new(rng.f32() * side, rng.f32() * side)))
+        .collect()
+}
+
+fn linear_scan(objects: &[(u32, Vec2)], player: Vec2) -> usize {
+    objects
+        .iter()
+        .filter(|(_, position)| position.distance(player) <= RADIUS)
+        .count()
+}
+
+// average time of a call
+fn measure(frames: u32, mut f: impl FnMut(u32)) -> Duration {
+    let start = Instant::now();
+    for frame in 0..frames {
+        f(frame);
+    }
+    start.elapsed() / frames
+}
+
+fn main() {
+    // `cargo test --all-targets` runs this too, a single frame is enough to see it works
+    let frames = if std::env::args().any(|arg| arg == "--bench") {
+        1000
+    } else {
+        1
+    };
+    let rng = fastrand::Rng::with_seed(42);
+
+    println!(
+        "{:>8} {:>12} {:>12} {:>12}",
+        "objects", "linear", "grid", "grid build"
+    );
+    for objects in [10, 1_000, 10_000] {
+        let level = level(&rng, objects);
+        let side = (objects as f32).sqrt() * SPACING;
+        let players: Vec<Vec2> = (0..frames)
+            .map(|_| Vec2::new(rng.f32() * side, rng.f32() * side))
+            .collect();
+
+        let mut grid = SpatialGrid::new(CELL_SIZE);
+        let build = measure(1, |_| {
+            for &(key, position) in &level {
+                grid.insert(key, position);
+            }
+        });
+
+        let linear = measure(frames, |frame| {
+            black_box(linear_scan(&level, players[frame as usize]));
+        });
+        let indexed = measure(frames, |frame| {
+            black_box(grid.within(players[frame as usize], RADIUS).len());
+        });
+
+        // both have to agree on what's around
+        for &player in &players {
+            assert_eq!(
+                linear_scan(&level, player),
+                grid.within(player, RADIUS).len()
+            );
+        }
+
+        println!(
+            "{:>8} {:>12?} {:>12?} {:>12?}",
+            objects, linear, indexed, build
+        );
+    }
+}
@@ -0,0 +1,186 @@
+// Short lines NPCs say on their own when the player walks by.
+//
+// Unlike dialogs they never take over the input, they float above the NPC
+// and fade away once the player leaves.
+
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use std::time::Duration;
+
+#[derive(Resource, Debug, Clone)]
+pub struct BarkSettings {
+    // how long an NPC stays quiet after a bark, unless its dialog file says otherwise
+    pub cooldown: Duration,
+    pub fade_out: Duration,
+}
+
+impl Default for BarkSettings {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::from_secs(15),
+            fade_out: Duration::from_millis(600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastBark {
+    line: usize,
+    at: Duration,
+}
+
+// When every NPC barked last, keyed by NPC name
+#[derive(Resource, Debug, Default)]
+pub struct BarkCooldowns {
+    last: HashMap<String, LastBark>,
+}
+
+impl BarkCooldowns {
+    pub fn is_ready(&self, npc: &str, now: Duration, cooldown: Duration) -> bool {
+        match self.last.get(npc) {
+            Some(last) => now.saturating_sub(last.at) >= cooldown,
+            None => true,
+        }
+    }
+
+    // Picks a line from the pool unless the NPC is still cooling down.
+    // `random` returns an index below the number it's given.
+    // The line said last time is not repeated while there are others to choose from.
+    pub fn pick<'a>(
+        &mut self,
+        npc: &str,
+        pool: &'a [String],
+        now: Duration,
+        cooldown: Duration,
+        random: impl FnOnce(usize) -> usize,
+    ) -> Option<&'a str> {
+        if pool.is_empty() || !self.is_ready(npc, now, cooldown) {
+            return None;
+        }
+
+        let line = match self.last.get(npc) {
+            Some(last) if pool.len() > 1 && last.line < pool.len() => {
+                // skip over the previous line
+                let idx = random(pool.len() - 1);
+                if idx >= last.line {
+                    idx + 1
+                } else {
+                    idx
+                }
+            }
+            _ => random(pool.len()),
+        };
+        self.last.insert(npc.into(), LastBark { line, at: now });
+        pool.get(line).map(String::as_str)
+    }
+}
+
+// Opacity going from 1 to 0 over a fixed time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fade {
+    duration: Duration,
+    elapsed: Duration,
+}
+
+impl Fade {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+    }
+
+    pub fn alpha(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 0.;
+        }
+        1. - self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Vec<String> {
+        vec!["Evening.".into(), "Cold one.".into(), "Move along.".into()]
+    }
+
+    #[test]
+    fn test_cooldown_per_npc() {
+        let mut cooldowns = BarkCooldowns::default();
+        let pool = pool();
+        let cooldown = Duration::from_secs(10);
+        let at = Duration::from_secs;
+
+        assert_eq!(
+            cooldowns.pick("Joe", &pool, at(1), cooldown, |_| 0),
+            Some("Evening.")
+        );
+        assert_eq!(cooldowns.pick("Joe", &pool, at(5), cooldown, |_| 0), None);
+        // others are not affected
+        assert!(cooldowns
+            .pick("Rue", &pool, at(5), cooldown, |_| 0)
+            .is_some());
+        assert!(!cooldowns.is_ready("Joe", at(10), cooldown));
+        assert!(cooldowns.is_ready("Joe", at(11), cooldown));
+
+        assert_eq!(cooldowns.pick("Moe", &[], at(1), cooldown, |_| 0), None);
+    }
+
+    #[test]
+    fn test_pick_does_not_repeat() {
+        let mut cooldowns = BarkCooldowns::default();
+        let pool = pool();
+
+        let mut said = vec![];
+        for (second, random) in [0, 0, 0, 1, 1].into_iter().enumerate() {
+            let now = Duration::from_secs(second as u64);
+            let line = cooldowns.pick("Joe", &pool, now, Duration::ZERO, |n| {
+                assert!(random < n);
+                random
+            });
+            said.push(line.unwrap());
+        }
+        assert_eq!(
+            said,
+            [
+                "Evening.",
+                "Cold one.",
+                "Evening.",
+                "Move along.",
+                "Cold one."
+            ]
+        );
+
+        // a single line is all there is to say
+        let single = vec!["Hm.".to_owned()];
+        for second in 0..2 {
+            let now = Duration::from_secs(second);
+            let line = cooldowns.pick("Rue", &single, now, Duration::ZERO, |_| 0);
+            assert_eq!(line, Some("Hm."));
+        }
+    }
+
+    #[test]
+    fn test_fade() {
+        let mut fade = Fade::new(Duration::from_millis(500));
+        assert_eq!(fade.alpha(), 1.);
+
+        fade.tick(Duration::from_millis(250));
+        assert!((fade.alpha() - 0.5).abs() < 1e-6);
+        assert!(!fade.is_finished());
+
+        fade.tick(Duration::from_secs(1));
+        assert_eq!(fade.alpha(), 0.);
+        assert!(fade.is_finished());
+    }
+}
@@ -0,0 +1,54 @@
+// Renders the dialog graph of an NPC as Graphviz DOT:
+//
+//     cargo run --bin dialog_dot -- NPC [OUTPUT]
+//
+// OUTPUT defaults to `<npc>.dot` in the current directory, `-` prints to stdout.
+
+use mistery::dialog::*;
+use mistery::dot::*;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let npc = match args.next() {
+        Some(npc) => npc,
+        None => {
+            eprintln!("usage: dialog_dot NPC [OUTPUT]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = args
+        .next()
+        .unwrap_or_else(|| format!("{}.dot", npc.to_lowercase()));
+
+    let library = match DialogLibrary::load_dir(DialogLibrary::default_dir()) {
+        Ok(library) => library,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let graph = match library.get(&npc) {
+        Some(graph) => graph,
+        None => {
+            let mut known: Vec<_> = library.iter().map(|graph| graph.npc.as_str()).collect();
+            known.sort();
+            eprintln!("error: no dialog for {}, known: {}", npc, known.join(", "));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let dot = to_dot(graph);
+    if output == "-" {
+        print!("{}", dot);
+        return ExitCode::SUCCESS;
+    }
+    let path = PathBuf::from(output);
+    if let Err(e) = std::fs::write(&path, dot) {
+        eprintln!("error: {}: {}", path.display(), e);
+        return ExitCode::FAILURE;
+    }
+    println!("wrote {}", path.display());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,81 @@
+// Checks dialog content without opening a window:
+//
+//     cargo run --bin validate_dialogs [ASSETS_DIR]
+//
+// Prints every problem found and exits with a failure if any of them is an error.
+
+use mistery::dialog::*;
+use mistery::flags::*;
+use mistery::npcs::*;
+use mistery::validate::*;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let assets = match std::env::args_os().nth(1) {
+        Some(dir) => PathBuf::from(dir),
+        None => bevy::asset::FileAssetIo::get_base_path().join("assets"),
+    };
+
+    let flags = match GameFlags::load(assets.join("flags.ron")) {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let paths = match dialog_files(assets.join("dialogs")) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // keep going past broken files to report as much as possible at once
+    let mut errors = 0;
+    let mut graphs: Vec<DialogGraph> = vec![];
+    for path in &paths {
+        match load_graph(path) {
+            Ok(graph) if graphs.iter().any(|other| other.npc == graph.npc) => {
+                errors += 1;
+                let e = DialogLoadError::DuplicateNpc {
+                    npc: graph.npc,
+                    path: path.clone(),
+                };
+                eprintln!("error: {}", e);
+            }
+            Ok(graph) => graphs.push(graph),
+            Err(e) => {
+                errors += 1;
+                eprintln!("error: {}", e);
+            }
+        }
+    }
+
+    let mut issues = validate_npcs(NPCS, &graphs, &flags);
+    for graph in &graphs {
+        issues.extend(validate_graph(graph, &flags));
+    }
+
+    let mut warnings = 0;
+    for issue in &issues {
+        match issue.severity {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+        }
+        eprintln!("{}", issue);
+    }
+    println!(
+        "checked {}x dialogs: {}x errors, {}x warnings",
+        paths.len(),
+        errors,
+        warnings
+    );
+
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,319 @@
+// Bodies moving among static colliders, stopping at them and sliding along them.
+//
+// Motion is swept, a body moving further than a wall is thick in a single frame
+// still stops at the wall. Polygons collide as their bounding boxes.
+
+use crate::shape::Shape;
+use bevy::math::Vec2;
+
+// left between a body and what it stopped at, so that sliding along a surface
+// doesn't count as hitting it again
+const SKIN: f32 = 0.01;
+// changes of direction within a single move, a corner takes two
+const MAX_SLIDES: usize = 4;
+
+// where along the motion a body hits a collider
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    // fraction of the motion done by then, from 0 to 1
+    pub time: f32,
+    // pointing out of the collider, towards the body
+    pub normal: Vec2,
+}
+
+// a box grown by a radius, which covers every shape there is
+#[derive(Debug, Clone, Copy)]
+struct RoundedBox {
+    center: Vec2,
+    half_size: Vec2,
+    radius: f32,
+}
+
+impl RoundedBox {
+    fn new(shape: &Shape, at: Vec2) -> Self {
+        let (center, half_size, radius) = match shape {
+            Shape::Point => (at, Vec2::ZERO, 0.),
+            Shape::Circle { radius } => (at, Vec2::ZERO, *radius),
+            Shape::Rect { half_size } => (at, *half_size, 0.),
+            Shape::Polygon { vertices } => {
+                let min = vertices
+                    .iter()
+                    .copied()
+                    .fold(Vec2::splat(f32::MAX), Vec2::min);
+                let max = vertices
+                    .iter()
+                    .copied()
+                    .fold(Vec2::splat(f32::MIN), Vec2::max);
+                (at + (min + max) / 2., (max - min) / 2., 0.)
+            }
+        };
+        Self {
+            center,
+            half_size,
+            radius,
+        }
+    }
+
+    // everywhere the center of `body` can't be without overlapping `self`
+    fn grown_by(self, body: RoundedBox, body_at: Vec2) -> Self {
+        Self {
+            // the body's box may be off its center
+            center: self.center - (body.center - body_at),
+            half_size: self.half_size + body.half_size,
+            radius: self.radius + body.radius,
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        // how far out of the box along each axis, negative within it
+        let outside = (point - self.center).abs() - self.half_size;
+        outside.max_element() < 0. || outside.max(Vec2::ZERO).length() < self.radius
+    }
+
+    fn ray_cast(&self, origin: Vec2, motion: Vec2) -> Option<Hit> {
+        if self.contains(origin) {
+            return None;
+        }
+        let (min, max) = (self.center - self.half_size, self.center + self.half_size);
+        let grown = |by: Vec2| ray_rect(origin, motion, min - by, max + by);
+        let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+        [
+            grown(Vec2::new(self.radius, 0.)),
+            grown(Vec2::new(0., self.radius)),
+        ]
+        .into_iter()
+        .chain(
+            corners
+                .into_iter()
+                .map(|corner| ray_circle(origin, motion, corner, self.radius)),
+        )
+        .flatten()
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+}
+
+fn ray_rect(origin: Vec2, motion: Vec2, min: Vec2, max: Vec2) -> Option<Hit> {
+    let (mut enter, mut exit) = (f32::MIN, f32::MAX);
+    let mut normal = Vec2::ZERO;
+    for axis in 0..2 {
+        if motion[axis] == 0. {
+            if origin[axis] <= min[axis] || origin[axis] >= max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - origin[axis]) / motion[axis];
+        let b = (max[axis] - origin[axis]) / motion[axis];
+        let (near, far) = if a < b { (a, b) } else { (b, a) };
+        if near > enter {
+            enter = near;
+            normal = Vec2::ZERO;
+            normal[axis] = -motion[axis].signum();
+        }
+        exit = exit.min(far);
+    }
+    if enter > exit || !(0. ..=1.).contains(&enter) {
+        return None;
+    }
+    Some(Hit {
+        time: enter,
+        normal,
+    })
+}
+
+fn ray_circle(origin: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<Hit> {
+    if radius <= 0. {
+        return None;
+    }
+    let offset = origin - center;
+    let a = motion.length_squared();
+    let b = 2. * offset.dot(motion);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - 4. * a * c;
+    if a == 0. || discriminant < 0. {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / (2. * a);
+    if !(0. ..=1.).contains(&time) {
+        return None;
+    }
+    Some(Hit {
+        time,
+        normal: (offset + motion * time) / radius,
+    })
+}
+
+// First thing `body` placed at `from` runs into moving by `motion`.
+// Colliders it already overlaps don't stop it, so whatever got stuck can get out.
+pub fn cast<'a>(
+    body: &Shape,
+    from: Vec2,
+    motion: Vec2,
+    colliders: impl IntoIterator<Item = (&'a Shape, Vec2)>,
+) -> Option<Hit> {
+    let body_box = RoundedBox::new(body, from);
+    colliders
+        .into_iter()
+        .filter_map(|(collider, at)| {
+            RoundedBox::new(collider, at)
+                .grown_by(body_box, from)
+                .ray_cast(from, motion)
+        })
+        // only what it moves into, not what it slides past
+        .filter(|hit| hit.normal.dot(motion) < 0.)
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+// Where `body` ends up trying to move by `motion`, stopping at colliders
+// and sliding along them with whatever motion is left.
+pub fn move_and_slide(
+    body: &Shape,
+    from: Vec2,
+    motion: Vec2,
+    colliders: &[(&Shape, Vec2)],
+) -> Vec2 {
+    let mut position = from;
+    let mut remaining = motion;
+    for _ in 0..MAX_SLIDES {
+        let length = remaining.length();
+        if length <= SKIN {
+            break;
+        }
+        let hit = match cast(body, position, remaining, colliders.iter().copied()) {
+            Some(hit) => hit,
+            None => {
+                position += remaining;
+                break;
+            }
+        };
+
+        let travel = (hit.time * length - SKIN).max(0.);
+        position += remaining / length * travel;
+        let left = remaining * (1. - hit.time);
+        remaining = left - hit.normal * left.dot(hit.normal);
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 0.1,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_stops_at_walls() {
+        let player = Shape::Circle { radius: 10. };
+        let wall = Shape::rect(Vec2::new(20., 200.));
+        let colliders = [(&wall, Vec2::new(100., 0.))];
+
+        // head on, right at the surface
+        assert_close(
+            move_and_slide(&player, Vec2::ZERO, Vec2::new(200., 0.), &colliders),
+            Vec2::new(80., 0.),
+        );
+        // far too fast for the wall to be anywhere in between frames
+        assert_close(
+            move_and_slide(&player, Vec2::ZERO, Vec2::new(10_000., 0.), &colliders),
+            Vec2::new(80., 0.),
+        );
+        // short of it, and away from it
+        assert_close(
+            move_and_slide(&player, Vec2::ZERO, Vec2::new(50., 0.), &colliders),
+            Vec2::new(50., 0.),
+        );
+        assert_close(
+            move_and_slide(&player, Vec2::new(80., 0.), Vec2::new(-30., 0.), &colliders),
+            Vec2::new(50., 0.),
+        );
+    }
+
+    #[test]
+    fn test_slides_along_surfaces() {
+        let player = Shape::Circle { radius: 10. };
+        let wall = Shape::rect(Vec2::new(20., 200.));
+        let colliders = [(&wall, Vec2::new(100., 0.))];
+
+        // diagonally into the wall, what's left goes along it
+        assert_close(
+            move_and_slide(&player, Vec2::ZERO, Vec2::new(100., 50.), &colliders),
+            Vec2::new(80., 50.),
+        );
+        // along it, touching
+        assert_close(
+            move_and_slide(&player, Vec2::new(80., 0.), Vec2::new(0., 60.), &colliders),
+            Vec2::new(80., 60.),
+        );
+        // round the corner, and past it
+        assert_close(
+            move_and_slide(
+                &player,
+                Vec2::new(80., 90.),
+                Vec2::new(0., 100.),
+                &colliders,
+            ),
+            Vec2::new(80., 190.),
+        );
+
+        // into the corner of two walls, stuck in both directions
+        let floor = Shape::rect(Vec2::new(200., 20.));
+        let colliders = [(&wall, Vec2::new(100., 0.)), (&floor, Vec2::new(0., 100.))];
+        assert_close(
+            move_and_slide(&player, Vec2::ZERO, Vec2::new(300., 300.), &colliders),
+            Vec2::new(80., 80.),
+        );
+    }
+
+    #[test]
+    fn test_shapes() {
+        let npc = Shape::Circle { radius: 20. };
+        let crate_ = Shape::rect(Vec2::new(40., 40.));
+
+        // circles meet on the line between their centers
+        let hit = cast(
+            &Shape::Circle { radius: 10. },
+            Vec2::ZERO,
+            Vec2::new(100., 0.),
+            [(&npc, Vec2::new(50., 0.))],
+        )
+        .unwrap();
+        assert!((hit.time - 0.2).abs() < 1e-4);
+        assert_close(hit.normal, Vec2::new(-1., 0.));
+
+        // boxes against boxes stop flat
+        assert_close(
+            move_and_slide(
+                &crate_,
+                Vec2::ZERO,
+                Vec2::new(0., 100.),
+                &[(&crate_, Vec2::new(30., 80.))],
+            ),
+            Vec2::new(0., 40.),
+        );
+        // points pass by a corner they only touch
+        assert!(cast(
+            &Shape::Point,
+            Vec2::new(-20., 20.),
+            Vec2::new(100., 0.),
+            [(&crate_, Vec2::ZERO)],
+        )
+        .is_none());
+        // overlapping already, free to get out
+        assert_close(
+            move_and_slide(
+                &npc,
+                Vec2::new(10., 0.),
+                Vec2::new(50., 0.),
+                &[(&crate_, Vec2::ZERO)],
+            ),
+            Vec2::new(60., 0.),
+        );
+    }
+}
@@ -0,0 +1,517 @@
+// Branching conversations, authored as data under assets/dialogs.
+//
+// Every file describes the graph of a single NPC, matched by `Name`:
+//
+//     (
+//         npc: "Joe",
+//         start: "greeting",
+//         nodes: {
+//             "greeting": (
+//                 text: "Evening, detective.",
+//                 choices: [
+//                     (text: "Where were you at midnight?", next: "alibi"),
+//                     (text: "Never mind."),
+//                 ],
+//             ),
+//             "alibi": (text: "At the docks, ask Rue."),
+//         },
+//     )
+//
+// A node either offers choices, continues to `next`, or ends the conversation.
+// A choice without `next` ends the conversation as well.
+//
+// Story progress (see flags.rs) shapes a conversation:
+//   - `entries` pick the first node whose condition holds, `start` otherwise
+//   - a choice with a `condition` is offered only while the condition holds
+//   - `effects` apply when a node is entered or a choice is made
+//
+//     entries: [(condition: "met_joe", node: "again")],
+//     ...
+//     (text: "Who's Moe?", condition: "clues >= 1", effects: ["asked_about_moe = true"]),
+//
+// Speakers may have portraits, images are paths under assets. A line shows the portrait
+// of its speaker, or the one it names, with an optional expression in place of the base image:
+//
+//     portraits: {
+//         "Joe": (image: "character.png", expressions: {"angry": "portraits/joe_angry.png"}),
+//     },
+//     ...
+//     "caught": (text: "Rue told you, huh?", expression: "angry"),
+//
+// Short lines an NPC says on its own when the player comes close, outside of any
+// conversation. One is picked at random, then the NPC stays quiet for a while:
+//
+//     barks: ["Evening, detective.", "Cold one tonight."],
+//     bark_cooldown: 20.,
+
+use crate::flags::{Condition, Effect, GameFlags};
+use bevy::log::warn;
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub type NodeId = String;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogGraph {
+    pub npc: String,
+    pub start: NodeId,
+    #[serde(default)]
+    pub entries: Vec<DialogEntry>,
+    #[serde(default)]
+    pub portraits: BTreeMap<String, Portrait>,
+    #[serde(default)]
+    pub barks: Vec<String>,
+    // in seconds, the default of BarkSettings otherwise
+    #[serde(default)]
+    pub bark_cooldown: Option<f32>,
+    pub nodes: BTreeMap<NodeId, DialogNode>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Portrait {
+    pub image: String,
+    #[serde(default)]
+    pub expressions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogEntry {
+    pub condition: Condition,
+    pub node: NodeId,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogNode {
+    // defaults to the NPC owning the graph
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<DialogChoice>,
+    #[serde(default)]
+    pub next: Option<NodeId>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    // defaults to the portrait of the speaker
+    #[serde(default)]
+    pub portrait: Option<String>,
+    #[serde(default)]
+    pub expression: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogChoice {
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<NodeId>,
+    #[serde(default)]
+    pub condition: Option<Condition>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+// conditions that fail to evaluate are reported and treated as false
+fn holds(condition: &Condition, flags: &GameFlags) -> bool {
+    condition.eval(flags).unwrap_or_else(|e| {
+        warn!("dialog condition `{}`: {}", condition.source(), e);
+        false
+    })
+}
+
+fn apply_effects(effects: &[Effect], flags: &mut GameFlags) {
+    for effect in effects {
+        if let Err(e) = effect.apply(flags) {
+            warn!("dialog effect `{}`: {}", effect.source(), e);
+        }
+    }
+}
+
+impl DialogChoice {
+    pub fn is_available(&self, flags: &GameFlags) -> bool {
+        match &self.condition {
+            Some(condition) => holds(condition, flags),
+            None => true,
+        }
+    }
+}
+
+impl DialogGraph {
+    // used for NPCs nobody has written anything for yet
+    pub fn fallback(npc: impl Into<String>) -> Self {
+        let npc = npc.into();
+        let start: NodeId = "start".into();
+        let node = DialogNode {
+            speaker: None,
+            text: format!("I'm {}", npc),
+            choices: vec![],
+            next: None,
+            effects: vec![],
+            portrait: None,
+            expression: None,
+        };
+
+        Self {
+            npc,
+            entries: vec![],
+            portraits: BTreeMap::new(),
+            barks: vec![],
+            bark_cooldown: None,
+            nodes: BTreeMap::from([(start.clone(), node)]),
+            start,
+        }
+    }
+
+    pub fn node(&self, id: &str) -> Option<&DialogNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn entry(&self, flags: &GameFlags) -> &NodeId {
+        self.entries
+            .iter()
+            .find(|entry| holds(&entry.condition, flags))
+            .map_or(&self.start, |entry| &entry.node)
+    }
+
+    pub fn speaker<'a>(&'a self, node: &'a DialogNode) -> &'a str {
+        node.speaker.as_deref().unwrap_or(&self.npc)
+    }
+
+    // image to show next to the line, unknown expressions fall back to the base image
+    pub fn portrait_image<'a>(&'a self, node: &'a DialogNode) -> Option<&'a str> {
+        let key = node.portrait.as_deref().unwrap_or(self.speaker(node));
+        let portrait = self.portraits.get(key)?;
+        let expression = node
+            .expression
+            .as_ref()
+            .and_then(|expression| portrait.expressions.get(expression));
+        Some(expression.unwrap_or(&portrait.image))
+    }
+}
+
+impl DialogNode {
+    pub fn is_terminal(&self) -> bool {
+        self.choices.is_empty() && self.next.is_none()
+    }
+}
+
+// Position of an ongoing conversation inside a graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogCursor {
+    node: Option<NodeId>,
+}
+
+impl DialogCursor {
+    pub fn start(graph: &DialogGraph, flags: &mut GameFlags) -> Self {
+        let mut cursor = Self { node: None };
+        cursor.enter(graph, Some(graph.entry(flags).clone()), flags);
+        cursor
+    }
+
+    fn enter(&mut self, graph: &DialogGraph, node: Option<NodeId>, flags: &mut GameFlags) {
+        self.node = node;
+        if let Some(node) = self.current(graph) {
+            apply_effects(&node.effects, flags);
+        }
+    }
+
+    pub fn node_id(&self) -> Option<&str> {
+        self.node.as_deref()
+    }
+
+    pub fn current<'a>(&self, graph: &'a DialogGraph) -> Option<&'a DialogNode> {
+        self.node.as_deref().and_then(|id| graph.node(id))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.node.is_none()
+    }
+
+    // choices of the current node available under `flags`, along with their indices
+    pub fn choices<'a>(
+        &self,
+        graph: &'a DialogGraph,
+        flags: &GameFlags,
+    ) -> Vec<(usize, &'a DialogChoice)> {
+        self.current(graph).map_or(vec![], |node| {
+            node.choices
+                .iter()
+                .enumerate()
+                .filter(|(_, choice)| choice.is_available(flags))
+                .collect()
+        })
+    }
+
+    // follows `next` of a node without choices
+    pub fn advance(&mut self, graph: &DialogGraph, flags: &mut GameFlags) {
+        let next = self.current(graph).and_then(|node| node.next.clone());
+        self.enter(graph, next, flags);
+    }
+
+    // follows the choice at `index`, out of range ends the conversation
+    pub fn choose(&mut self, graph: &DialogGraph, index: usize, flags: &mut GameFlags) {
+        let choice = self.current(graph).and_then(|node| node.choices.get(index));
+        if let Some(choice) = choice {
+            apply_effects(&choice.effects, flags);
+        }
+        let next = choice.and_then(|choice| choice.next.clone());
+        self.enter(graph, next, flags);
+    }
+
+    pub fn finish(&mut self) {
+        self.node = None;
+    }
+}
+
+#[derive(Debug)]
+pub enum DialogLoadError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    UnsupportedFormat {
+        path: PathBuf,
+    },
+    DuplicateNpc {
+        npc: String,
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for DialogLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DialogLoadError::*;
+        match self {
+            Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+            UnsupportedFormat { path } => {
+                write!(f, "{}: expected a .ron or .json file", path.display())
+            }
+            DuplicateNpc { npc, path } => {
+                write!(
+                    f,
+                    "{}: dialog for {} is already defined",
+                    path.display(),
+                    npc
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DialogLoadError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DialogFormat {
+    Ron,
+    Json,
+}
+
+impl DialogFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "ron" => Some(Self::Ron),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_graph(source: &str, format: DialogFormat) -> Result<DialogGraph, String> {
+    match format {
+        DialogFormat::Ron => ron::Options::default()
+            // lets writers omit Some(..) around optional fields
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .map_err(|e| e.to_string()),
+        DialogFormat::Json => serde_json::from_str(source).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn load_graph(path: impl AsRef<Path>) -> Result<DialogGraph, DialogLoadError> {
+    let path = path.as_ref();
+    let format = DialogFormat::from_path(path)
+        .ok_or_else(|| DialogLoadError::UnsupportedFormat { path: path.into() })?;
+    let source = std::fs::read_to_string(path).map_err(|source| DialogLoadError::Io {
+        path: path.into(),
+        source,
+    })?;
+
+    parse_graph(&source, format).map_err(|message| DialogLoadError::Parse {
+        path: path.into(),
+        message,
+    })
+}
+
+// Dialog files in `dir`, sorted for deterministic error reporting.
+// Files with unknown extensions are skipped.
+pub fn dialog_files(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, DialogLoadError> {
+    let dir = dir.as_ref();
+    let io_err = |source| DialogLoadError::Io {
+        path: dir.into(),
+        source,
+    };
+
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(io_err)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err)?;
+    paths.retain(|path| DialogFormat::from_path(path).is_some());
+    paths.sort();
+    Ok(paths)
+}
+
+// Dialog graphs keyed by the `Name` of their NPC
+#[derive(Resource, Debug, Default)]
+pub struct DialogLibrary {
+    graphs: HashMap<String, DialogGraph>,
+}
+
+impl DialogLibrary {
+    pub fn default_dir() -> PathBuf {
+        bevy::asset::FileAssetIo::get_base_path()
+            .join("assets")
+            .join("dialogs")
+    }
+
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, DialogLoadError> {
+        let mut library = Self::default();
+        for path in dialog_files(dir)? {
+            let graph = load_graph(&path)?;
+            if library.graphs.contains_key(&graph.npc) {
+                return Err(DialogLoadError::DuplicateNpc {
+                    npc: graph.npc,
+                    path,
+                });
+            }
+            library.insert(graph);
+        }
+        Ok(library)
+    }
+
+    pub fn insert(&mut self, graph: DialogGraph) {
+        self.graphs.insert(graph.npc.clone(), graph);
+    }
+
+    pub fn get(&self, npc: &str) -> Option<&DialogGraph> {
+        self.graphs.get(npc)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DialogGraph> {
+        self.graphs.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::FlagValue;
+
+    const JOE: &str = r#"(
+        npc: "Joe",
+        start: "greeting",
+        entries: [(condition: "met_joe", node: "again")],
+        portraits: {
+            "Joe": (image: "joe.png", expressions: {"angry": "joe_angry.png"}),
+        },
+        nodes: {
+            "greeting": (
+                text: "Evening, detective.",
+                effects: ["met_joe = true"],
+                choices: [
+                    (text: "Where were you?", next: "alibi"),
+                    (text: "Who's Moe?", condition: "clues >= 1", next: "bye"),
+                    (text: "Never mind."),
+                ],
+            ),
+            "again": (text: "You again?", expression: "angry"),
+            "alibi": (text: "At the docks.", next: "bye"),
+            "bye": (
+                speaker: "Rue",
+                text: "He was.",
+                choices: [(text: "Thanks.", effects: ["clues += 1"])],
+            ),
+        },
+    )"#;
+
+    fn flags() -> GameFlags {
+        let mut flags = GameFlags::default();
+        flags.declare("met_joe", FlagValue::Bool(false));
+        flags.declare("clues", FlagValue::Int(0));
+        flags
+    }
+
+    #[test]
+    fn test_parse_ron_graph() {
+        let graph = parse_graph(JOE, DialogFormat::Ron).unwrap();
+
+        assert_eq!(graph.npc, "Joe");
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.node("greeting").unwrap().choices[2].next, None);
+        assert_eq!(graph.speaker(graph.node("alibi").unwrap()), "Joe");
+        assert_eq!(graph.speaker(graph.node("bye").unwrap()), "Rue");
+    }
+
+    #[test]
+    fn test_portrait_image() {
+        let graph = parse_graph(JOE, DialogFormat::Ron).unwrap();
+        let image = |node| graph.portrait_image(graph.node(node).unwrap());
+
+        assert_eq!(image("greeting"), Some("joe.png"));
+        assert_eq!(image("again"), Some("joe_angry.png"));
+        // Rue has no portrait
+        assert_eq!(image("bye"), None);
+    }
+
+    #[test]
+    fn test_parse_reports_invalid_condition() {
+        let source = JOE.replace("clues >= 1", "clues >=");
+        let error = parse_graph(&source, DialogFormat::Ron).unwrap_err();
+
+        assert!(error.contains("expected a value"), "{}", error);
+    }
+
+    #[test]
+    fn test_cursor_walks_graph() {
+        let graph = parse_graph(JOE, DialogFormat::Ron).unwrap();
+        let mut flags = flags();
+        let mut cursor = DialogCursor::start(&graph, &mut flags);
+
+        assert_eq!(cursor.node_id(), Some("greeting"));
+        assert_eq!(flags.get("met_joe"), Ok(&FlagValue::Bool(true)));
+        let available = cursor.choices(&graph, &flags);
+        assert_eq!(
+            available.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 2]
+        );
+
+        cursor.choose(&graph, 0, &mut flags);
+        assert_eq!(cursor.node_id(), Some("alibi"));
+        cursor.advance(&graph, &mut flags);
+        assert_eq!(cursor.node_id(), Some("bye"));
+        cursor.choose(&graph, 0, &mut flags);
+        assert!(cursor.is_finished());
+        assert_eq!(flags.get("clues"), Ok(&FlagValue::Int(1)));
+
+        let cursor = DialogCursor::start(&graph, &mut flags);
+        assert_eq!(cursor.node_id(), Some("again"));
+    }
+
+    #[test]
+    fn test_load_shipped_dialogs() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let library = DialogLibrary::load_dir(assets.join("dialogs")).unwrap();
+        GameFlags::load(assets.join("flags.ron")).unwrap();
+
+        assert!(library.get("Joe").is_some());
+        assert!(library.get("Rue").is_some());
+    }
+}
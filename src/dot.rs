@@ -0,0 +1,153 @@
+// Dialog graphs as Graphviz DOT, for reviewing the flow of a conversation as a diagram:
+//
+//     dot -Tsvg joe.dot -o joe.svg
+
+use crate::dialog::{DialogGraph, DialogNode};
+use crate::text_layout::wrap;
+use std::fmt::Write as _;
+
+// in characters, Graphviz doesn't wrap labels by itself
+const LABEL_WIDTH: f32 = 40.;
+
+// where conversations come from and where they go, not nodes of the graph
+const START: &str = "__start";
+const END: &str = "__end";
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn wrapped(text: &str) -> String {
+    wrap(text, LABEL_WIDTH, |line| line.chars().count() as f32).join("\n")
+}
+
+fn node_label(graph: &DialogGraph, node: &DialogNode) -> String {
+    let mut label = format!("{}:\n{}", graph.speaker(node), wrapped(&node.text));
+    for effect in &node.effects {
+        write!(label, "\n[{}]", effect.source()).unwrap();
+    }
+    label
+}
+
+pub fn to_dot(graph: &DialogGraph) -> String {
+    let mut dot = String::new();
+    let mut line = |text: String| {
+        dot.push_str(&text);
+        dot.push('\n');
+    };
+
+    line(format!("digraph {} {{", quote(&graph.npc)));
+    line("    node [shape=box];".into());
+    line(format!("    {} [shape=point];", START));
+    line(format!("    {} [shape=doublecircle, label=\"end\"];", END));
+
+    line(format!("    {} -> {};", START, quote(&graph.start)));
+    for entry in &graph.entries {
+        line(format!(
+            "    {} -> {} [label={}, style=dashed];",
+            START,
+            quote(&entry.node),
+            quote(&format!("[{}]", entry.condition.source()))
+        ));
+    }
+
+    for (id, node) in &graph.nodes {
+        line(format!(
+            "    {} [label={}];",
+            quote(id),
+            quote(&node_label(graph, node))
+        ));
+
+        for choice in &node.choices {
+            let mut label = wrapped(&choice.text);
+            if let Some(condition) = &choice.condition {
+                write!(label, "\n[{}]", condition.source()).unwrap();
+            }
+            for effect in &choice.effects {
+                write!(label, "\n[{}]", effect.source()).unwrap();
+            }
+            let target = match &choice.next {
+                Some(next) => quote(next),
+                None => END.into(),
+            };
+            line(format!(
+                "    {} -> {} [label={}];",
+                quote(id),
+                target,
+                quote(&label)
+            ));
+        }
+
+        // followed when none of the choices are available, which always
+        // happens without choices and may happen when all of them are conditional
+        let otherwise = match &node.next {
+            Some(next) => Some(quote(next)),
+            None if node.choices.iter().all(|choice| choice.condition.is_some()) => {
+                Some(END.into())
+            }
+            None => None,
+        };
+        if let Some(target) = otherwise {
+            line(format!("    {} -> {};", quote(id), target));
+        }
+    }
+
+    line("}".into());
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialog::{parse_graph, DialogFormat};
+
+    #[test]
+    fn test_to_dot() {
+        let graph = parse_graph(
+            r#"(
+                npc: "Joe",
+                start: "greeting",
+                entries: [(condition: "met_joe", node: "again")],
+                nodes: {
+                    "greeting": (
+                        text: "Evening, \"detective\".",
+                        effects: ["met_joe = true"],
+                        choices: [
+                            (text: "Who's Moe?", condition: "clues >= 1", next: "again"),
+                            (text: "Never mind.", effects: ["clues += 1"]),
+                        ],
+                    ),
+                    "again": (speaker: "Rue", text: "You again?"),
+                },
+            )"#,
+            DialogFormat::Ron,
+        )
+        .unwrap();
+
+        let expected = r#"digraph "Joe" {
+    node [shape=box];
+    __start [shape=point];
+    __end [shape=doublecircle, label="end"];
+    __start -> "greeting";
+    __start -> "again" [label="[met_joe]", style=dashed];
+    "again" [label="Rue:\nYou again?"];
+    "again" -> __end;
+    "greeting" [label="Joe:\nEvening, \"detective\".\n[met_joe = true]"];
+    "greeting" -> "again" [label="Who's Moe?\n[clues >= 1]"];
+    "greeting" -> __end [label="Never mind.\n[clues += 1]"];
+}
+"#;
+        assert_eq!(to_dot(&graph), expected);
+    }
+}
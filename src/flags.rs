@@ -0,0 +1,755 @@
+// Story progress: named flags that dialog and level setup can read and write.
+//
+// Flags are declared with their initial values in assets/flags.ron,
+// the value a flag is declared with fixes its type.
+//
+// Conditions are small expressions over flags:
+//
+//     met_joe && clues >= 2
+//     !moe_arrested || suspect == 'Moe'
+//
+// Effects assign to a single flag:
+//
+//     met_joe = true
+//     clues += 1
+
+use bevy::prelude::Resource;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum FlagValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+impl FlagValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FlagValue::Bool(_) => "bool",
+            FlagValue::Int(_) => "int",
+            FlagValue::Str(_) => "string",
+        }
+    }
+}
+
+impl fmt::Display for FlagValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagValue::Bool(value) => write!(f, "{}", value),
+            FlagValue::Int(value) => write!(f, "{}", value),
+            FlagValue::Str(value) => write!(f, "'{}'", value),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct GameFlags {
+    values: BTreeMap<String, FlagValue>,
+}
+
+impl GameFlags {
+    pub fn default_path() -> PathBuf {
+        bevy::asset::FileAssetIo::get_base_path()
+            .join("assets")
+            .join("flags.ron")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        ron::from_str(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn declare(&mut self, name: impl Into<String>, value: FlagValue) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn is_declared(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&FlagValue, EvalError> {
+        self.values
+            .get(name)
+            .ok_or_else(|| EvalError::UndefinedFlag(name.into()))
+    }
+
+    // only declared flags can be set, and only to a value of the same type
+    pub fn set(&mut self, name: &str, value: FlagValue) -> Result<(), EvalError> {
+        let current = self
+            .values
+            .get_mut(name)
+            .ok_or_else(|| EvalError::UndefinedFlag(name.into()))?;
+        if current.type_name() != value.type_name() {
+            return Err(EvalError::TypeMismatch {
+                context: format!("assignment to {}", name),
+                expected: current.type_name(),
+                found: value.type_name(),
+            });
+        }
+        *current = value;
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FlagValue)> {
+        self.values.iter()
+    }
+
+    // parses and evaluates a condition in one go
+    pub fn check(&self, condition: &str) -> Result<bool, FlagError> {
+        let condition = Condition::parse(condition)?;
+        Ok(condition.eval(self)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub source: String,
+    // byte offset into `source`
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at column {} in `{}`",
+            self.message,
+            self.position + 1,
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    UndefinedFlag(String),
+    TypeMismatch {
+        context: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UndefinedFlag(name) => write!(f, "flag {} is not declared", name),
+            EvalError::TypeMismatch {
+                context,
+                expected,
+                found,
+            } => write!(f, "{} expects {}, got {}", context, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlagError {
+    Parse(ParseError),
+    Eval(EvalError),
+}
+
+impl fmt::Display for FlagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagError::Parse(e) => e.fmt(f),
+            FlagError::Eval(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FlagError {}
+
+impl From<ParseError> for FlagError {
+    fn from(e: ParseError) -> Self {
+        FlagError::Parse(e)
+    }
+}
+
+impl From<EvalError> for FlagError {
+    fn from(e: EvalError) -> Self {
+        FlagError::Eval(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        use BinOp::*;
+        match self {
+            And => "&&",
+            Or => "||",
+            Eq => "==",
+            Ne => "!=",
+            Lt => "<",
+            Le => "<=",
+            Gt => ">",
+            Ge => ">=",
+            Add => "+",
+            Sub => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(FlagValue),
+    Flag(String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+fn expect_bool(value: FlagValue, context: &str) -> Result<bool, EvalError> {
+    match value {
+        FlagValue::Bool(value) => Ok(value),
+        other => Err(EvalError::TypeMismatch {
+            context: context.into(),
+            expected: "bool",
+            found: other.type_name(),
+        }),
+    }
+}
+
+fn expect_int(value: FlagValue, context: &str) -> Result<i64, EvalError> {
+    match value {
+        FlagValue::Int(value) => Ok(value),
+        other => Err(EvalError::TypeMismatch {
+            context: context.into(),
+            expected: "int",
+            found: other.type_name(),
+        }),
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(source)?;
+        let expr = parser.expr()?;
+        parser.expect_end()?;
+        Ok(expr)
+    }
+
+    pub fn eval(&self, flags: &GameFlags) -> Result<FlagValue, EvalError> {
+        use BinOp::*;
+
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Flag(name) => flags.get(name).cloned(),
+            Expr::Not(expr) => Ok(FlagValue::Bool(!expect_bool(expr.eval(flags)?, "!")?)),
+            Expr::Neg(expr) => Ok(FlagValue::Int(
+                expect_int(expr.eval(flags)?, "-")?.saturating_neg(),
+            )),
+            // short-circuits like the rust counterparts
+            Expr::Binary(op @ (And | Or), lhs, rhs) => {
+                let lhs = expect_bool(lhs.eval(flags)?, op.symbol())?;
+                let value = match op {
+                    And if !lhs => false,
+                    Or if lhs => true,
+                    _ => expect_bool(rhs.eval(flags)?, op.symbol())?,
+                };
+                Ok(FlagValue::Bool(value))
+            }
+            Expr::Binary(op @ (Eq | Ne), lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(flags)?, rhs.eval(flags)?);
+                if lhs.type_name() != rhs.type_name() {
+                    return Err(EvalError::TypeMismatch {
+                        context: op.symbol().into(),
+                        expected: lhs.type_name(),
+                        found: rhs.type_name(),
+                    });
+                }
+                Ok(FlagValue::Bool((lhs == rhs) == (*op == Eq)))
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = expect_int(lhs.eval(flags)?, op.symbol())?;
+                let rhs = expect_int(rhs.eval(flags)?, op.symbol())?;
+                Ok(match op {
+                    Lt => FlagValue::Bool(lhs < rhs),
+                    Le => FlagValue::Bool(lhs <= rhs),
+                    Gt => FlagValue::Bool(lhs > rhs),
+                    Ge => FlagValue::Bool(lhs >= rhs),
+                    Add => FlagValue::Int(lhs.saturating_add(rhs)),
+                    Sub => FlagValue::Int(lhs.saturating_sub(rhs)),
+                    And | Or | Eq | Ne => unreachable!(),
+                })
+            }
+        }
+    }
+
+    // names of every flag the expression reads
+    pub fn flags(&self) -> Vec<&str> {
+        match self {
+            Expr::Literal(_) => vec![],
+            Expr::Flag(name) => vec![name.as_str()],
+            Expr::Not(expr) | Expr::Neg(expr) => expr.flags(),
+            Expr::Binary(_, lhs, rhs) => {
+                let mut flags = lhs.flags();
+                flags.extend(rhs.flags());
+                flags
+            }
+        }
+    }
+}
+
+// An expression that must evaluate to a bool, keeps its source for reporting
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            source: source.into(),
+            expr: Expr::parse(source)?,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn flags(&self) -> Vec<&str> {
+        self.expr.flags()
+    }
+
+    pub fn eval(&self, flags: &GameFlags) -> Result<bool, EvalError> {
+        expect_bool(self.expr.eval(flags)?, "condition")
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = ParseError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Condition::parse(&source)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Set,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Effect {
+    source: String,
+    flag: String,
+    op: AssignOp,
+    value: Expr,
+}
+
+impl Effect {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(source)?;
+        let flag = parser.ident()?;
+        let op = match parser.next() {
+            Some((_, Token::Assign)) => AssignOp::Set,
+            Some((_, Token::AddAssign)) => AssignOp::Add,
+            Some((_, Token::SubAssign)) => AssignOp::Sub,
+            token => return Err(parser.error_at(token, "expected `=`, `+=` or `-=`")),
+        };
+        let value = parser.expr()?;
+        parser.expect_end()?;
+
+        Ok(Self {
+            source: source.into(),
+            flag,
+            op,
+            value,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn flag(&self) -> &str {
+        &self.flag
+    }
+
+    // the assigned flag followed by every flag the value reads
+    pub fn flags(&self) -> Vec<&str> {
+        let mut flags = vec![self.flag.as_str()];
+        flags.extend(self.value.flags());
+        flags
+    }
+
+    pub fn apply(&self, flags: &mut GameFlags) -> Result<(), EvalError> {
+        let value = self.value.eval(flags)?;
+        let value = match self.op {
+            AssignOp::Set => value,
+            AssignOp::Add | AssignOp::Sub => {
+                let context = self.source.as_str();
+                let current = expect_int(flags.get(&self.flag)?.clone(), context)?;
+                let delta = expect_int(value, context)?;
+                FlagValue::Int(match self.op {
+                    AssignOp::Add => current.saturating_add(delta),
+                    _ => current.saturating_sub(delta),
+                })
+            }
+        };
+        flags.set(&self.flag, value)
+    }
+}
+
+impl TryFrom<String> for Effect {
+    type Error = ParseError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Effect::parse(&source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    Op(BinOp),
+    Not,
+    LParen,
+    RParen,
+    Assign,
+    AddAssign,
+    SubAssign,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let error = |position, message: &str| ParseError {
+        source: source.into(),
+        position,
+        message: message.into(),
+    };
+
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = source[pos..].chars().next().unwrap();
+        let peek = bytes.get(pos + 1).map(|&b| b as char);
+
+        let token = match (c, peek) {
+            (c, _) if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            (c, _) if c.is_ascii_alphabetic() || c == '_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_')
+                {
+                    pos += 1;
+                }
+                tokens.push((start, Token::Ident(source[start..pos].into())));
+                continue;
+            }
+            (c, _) if c.is_ascii_digit() => {
+                while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                let value = source[start..pos]
+                    .parse()
+                    .map_err(|_| error(start, "integer is too large"))?;
+                tokens.push((start, Token::Int(value)));
+                continue;
+            }
+            // single quotes spare writers from escaping inside RON strings
+            (quote @ ('\'' | '"'), _) => {
+                let end = source[start + 1..]
+                    .find(quote)
+                    .ok_or_else(|| error(start, "unterminated string"))?;
+                pos = start + 1 + end + 1;
+                tokens.push((start, Token::Str(source[start + 1..pos - 1].into())));
+                continue;
+            }
+            ('&', Some('&')) => Token::Op(BinOp::And),
+            ('|', Some('|')) => Token::Op(BinOp::Or),
+            ('=', Some('=')) => Token::Op(BinOp::Eq),
+            ('!', Some('=')) => Token::Op(BinOp::Ne),
+            ('<', Some('=')) => Token::Op(BinOp::Le),
+            ('>', Some('=')) => Token::Op(BinOp::Ge),
+            ('+', Some('=')) => Token::AddAssign,
+            ('-', Some('=')) => Token::SubAssign,
+            ('<', _) => Token::Op(BinOp::Lt),
+            ('>', _) => Token::Op(BinOp::Gt),
+            ('+', _) => Token::Op(BinOp::Add),
+            ('-', _) => Token::Op(BinOp::Sub),
+            ('!', _) => Token::Not,
+            ('=', _) => Token::Assign,
+            ('(', _) => Token::LParen,
+            (')', _) => Token::RParen,
+            _ => return Err(error(start, &format!("unexpected character `{}`", c))),
+        };
+
+        pos += match token {
+            Token::Op(BinOp::Lt | BinOp::Gt | BinOp::Add | BinOp::Sub)
+            | Token::Not
+            | Token::Assign
+            | Token::LParen
+            | Token::RParen => 1,
+            _ => 2,
+        };
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+// Recursive descent, from the loosest binding operator to the tightest:
+//
+//     or      := and ("||" and)*
+//     and     := cmp ("&&" cmp)*
+//     cmp     := sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//     sum     := unary (("+" | "-") unary)*
+//     unary   := ("!" | "-") unary | primary
+//     primary := int | string | true | false | flag | "(" or ")"
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, ParseError> {
+        Ok(Self {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+        })
+    }
+
+    fn error_at(&self, token: Option<(usize, Token)>, message: &str) -> ParseError {
+        let (position, message) = match token {
+            Some((position, _)) => (position, message.to_owned()),
+            None => (
+                self.source.len(),
+                format!("{}, found end of input", message),
+            ),
+        };
+        ParseError {
+            source: self.source.into(),
+            position,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect_end(&mut self) -> Result<(), ParseError> {
+        match self.next() {
+            None => Ok(()),
+            token => Err(self.error_at(token, "unexpected token")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some((_, Token::Ident(name))) => Ok(name),
+            token => Err(self.error_at(token, "expected a flag name")),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[BinOp],
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+        repeat: bool,
+    ) -> Result<Expr, ParseError> {
+        let mut lhs = operand(self)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            if !ops.contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(operand(self)?));
+            if !repeat {
+                break;
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[BinOp::Or], Self::and, true)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[BinOp::And], Self::cmp, true)
+    }
+
+    fn cmp(&mut self) -> Result<Expr, ParseError> {
+        use BinOp::*;
+        self.binary(&[Eq, Ne, Lt, Le, Gt, Ge], Self::sum, false)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[BinOp::Add, BinOp::Sub], Self::unary, true)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Op(BinOp::Sub)) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some((_, Token::Int(value))) => Ok(Expr::Literal(FlagValue::Int(value))),
+            Some((_, Token::Str(value))) => Ok(Expr::Literal(FlagValue::Str(value))),
+            Some((_, Token::Ident(name))) => Ok(match name.as_str() {
+                "true" => Expr::Literal(FlagValue::Bool(true)),
+                "false" => Expr::Literal(FlagValue::Bool(false)),
+                _ => Expr::Flag(name),
+            }),
+            Some((_, Token::LParen)) => {
+                let expr = self.expr()?;
+                match self.next() {
+                    Some((_, Token::RParen)) => Ok(expr),
+                    token => Err(self.error_at(token, "expected `)`")),
+                }
+            }
+            token => Err(self.error_at(token, "expected a value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags() -> GameFlags {
+        let mut flags = GameFlags::default();
+        flags.declare("met_joe", FlagValue::Bool(true));
+        flags.declare("clues", FlagValue::Int(1));
+        flags.declare("suspect", FlagValue::Str("Moe".into()));
+        flags
+    }
+
+    #[test]
+    fn test_conditions() {
+        let flags = flags();
+        // input, expected output
+        let cases: &[(&str, bool)] = &[
+            ("met_joe", true),
+            ("!met_joe", false),
+            ("met_joe && clues >= 2", false),
+            ("met_joe && clues + 1 >= 2", true),
+            ("clues < 2 || undeclared", true),
+            ("!(clues == 1) || suspect != 'Moe'", false),
+            ("suspect == \"Moe\"", true),
+            ("-clues == 0 - 1", true),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(flags.check(source), Ok(*expected), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_condition_errors() {
+        let flags = flags();
+
+        assert_eq!(
+            flags.check("met_moe"),
+            Err(FlagError::Eval(EvalError::UndefinedFlag("met_moe".into())))
+        );
+        assert!(matches!(
+            flags.check("clues && met_joe"),
+            Err(FlagError::Eval(EvalError::TypeMismatch { .. }))
+        ));
+        assert!(matches!(
+            flags.check("clues"),
+            Err(FlagError::Eval(EvalError::TypeMismatch { .. }))
+        ));
+
+        let error = Condition::parse("met_joe && (clues > 1").unwrap_err();
+        assert_eq!(error.position, 21);
+        assert_eq!(
+            error.to_string(),
+            "expected `)`, found end of input at column 22 in `met_joe && (clues > 1`"
+        );
+        assert_eq!(Condition::parse("met_joe # 1").unwrap_err().position, 8);
+        assert_eq!(Condition::parse("clues > > 1").unwrap_err().position, 8);
+    }
+
+    #[test]
+    fn test_effects() {
+        let mut flags = flags();
+
+        Effect::parse("clues += 2")
+            .unwrap()
+            .apply(&mut flags)
+            .unwrap();
+        Effect::parse("met_joe = !met_joe")
+            .unwrap()
+            .apply(&mut flags)
+            .unwrap();
+        Effect::parse("suspect = 'Joe'")
+            .unwrap()
+            .apply(&mut flags)
+            .unwrap();
+
+        assert_eq!(flags.get("clues"), Ok(&FlagValue::Int(3)));
+        assert_eq!(flags.get("met_joe"), Ok(&FlagValue::Bool(false)));
+        assert_eq!(flags.get("suspect"), Ok(&FlagValue::Str("Joe".into())));
+
+        let effect = Effect::parse("clues = true").unwrap();
+        assert!(matches!(
+            effect.apply(&mut flags),
+            Err(EvalError::TypeMismatch { .. })
+        ));
+        assert_eq!(flags.get("clues"), Ok(&FlagValue::Int(3)));
+        assert!(Effect::parse("clues == 1").is_err());
+    }
+}
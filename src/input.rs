@@ -0,0 +1,649 @@
+// What the player can do, and the keys and buttons that do it.
+//
+// Systems read actions from `Input<Action>` rather than raw keys, so that bindings
+// can change while the game runs. Every frame `update_actions` presses and releases
+// actions after whatever is bound to them.
+//
+// Gamepads come and go at any time, `Controllers` keeps up with them from their events.
+
+use bevy::input::gamepad::{
+    Gamepad, GamepadAxisType, GamepadButton, GamepadButtonType, GamepadEvent, GamepadEventType,
+};
+use bevy::input::keyboard::KeyCode;
+use bevy::input::mouse::MouseButton;
+use bevy::input::Input;
+use bevy::math::Vec2;
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    MoveTo,
+    Interact,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    PageUp,
+    PageDown,
+    Confirm,
+    Cancel,
+    Pause,
+    MainMenu,
+    Settings,
+    History,
+    Fullscreen,
+    ScaleUp,
+    ScaleDown,
+}
+
+// Actions only clash with the ones they may be taken along with.
+// Walking around and picking a choice never happen at once, so they can share keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    // anywhere
+    Global,
+    // walking around the level
+    Gameplay,
+    // dialogs, history and the like
+    Menu,
+}
+
+impl Context {
+    fn overlaps(self, other: Context) -> bool {
+        self == other || self == Context::Global || other == Context::Global
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 22] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::MoveTo,
+        Action::Interact,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Confirm,
+        Action::Cancel,
+        Action::Pause,
+        Action::MainMenu,
+        Action::Settings,
+        Action::History,
+        Action::Fullscreen,
+        Action::ScaleUp,
+        Action::ScaleDown,
+    ];
+
+    pub fn context(self) -> Context {
+        use Action::*;
+        match self {
+            MoveUp | MoveDown | MoveLeft | MoveRight | Sprint | MoveTo | Interact => {
+                Context::Gameplay
+            }
+            MenuUp | MenuDown | MenuLeft | MenuRight | PageUp | PageDown | Confirm | Cancel => {
+                Context::Menu
+            }
+            Pause | MainMenu | Settings | History | Fullscreen | ScaleUp | ScaleDown => {
+                Context::Global
+            }
+        }
+    }
+
+    // rebound along with it, the key that starts a conversation is the one that keeps it going
+    fn linked(self) -> Option<Action> {
+        match self {
+            Action::Interact => Some(Action::Confirm),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // on any gamepad
+    Gamepad(GamepadButtonType),
+}
+
+// as shown to the player
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GamepadButtonType as Pad;
+        match *self {
+            Binding::Key(key) => {
+                use KeyCode::*;
+                let digits = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+                match (key, digits.iter().position(|&digit| digit == key)) {
+                    (_, Some(digit)) => write!(f, "{}", digit),
+                    (Return, _) => write!(f, "Enter"),
+                    (Back, _) => write!(f, "Backspace"),
+                    _ => write!(f, "{:?}", key),
+                }
+            }
+            Binding::Mouse(MouseButton::Left) => write!(f, "Left click"),
+            Binding::Mouse(MouseButton::Right) => write!(f, "Right click"),
+            Binding::Mouse(MouseButton::Middle) => write!(f, "Middle click"),
+            Binding::Mouse(MouseButton::Other(button)) => write!(f, "Mouse {}", button),
+            // as labeled on most controllers
+            Binding::Gamepad(Pad::South) => write!(f, "A"),
+            Binding::Gamepad(Pad::East) => write!(f, "B"),
+            Binding::Gamepad(Pad::West) => write!(f, "X"),
+            Binding::Gamepad(Pad::North) => write!(f, "Y"),
+            Binding::Gamepad(button) => write!(f, "{:?}", button),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingConflict {
+    pub binding: Binding,
+    // what it's bound to already
+    pub action: Action,
+}
+
+impl fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is already bound to {:?}", self.binding, self.action)
+    }
+}
+
+impl std::error::Error for BindingConflict {}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct InputMap {
+    // the first binding of an action is the one shown to the player
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Action::*;
+        use Binding::{Gamepad as Pad, Key, Mouse};
+        use GamepadButtonType as P;
+        use KeyCode as K;
+        let bindings = [
+            (MoveUp, vec![Key(K::W), Key(K::Up), Pad(P::DPadUp)]),
+            (MoveDown, vec![Key(K::S), Key(K::Down), Pad(P::DPadDown)]),
+            (MoveLeft, vec![Key(K::A), Key(K::Left), Pad(P::DPadLeft)]),
+            (MoveRight, vec![Key(K::D), Key(K::Right), Pad(P::DPadRight)]),
+            (Sprint, vec![Key(K::LShift), Pad(P::LeftThumb)]),
+            // walking to wherever is clicked
+            (MoveTo, vec![Mouse(MouseButton::Left)]),
+            (Interact, vec![Key(K::E), Key(K::Return), Pad(P::South)]),
+            (MenuUp, vec![Key(K::Up), Pad(P::DPadUp)]),
+            (MenuDown, vec![Key(K::Down), Pad(P::DPadDown)]),
+            (MenuLeft, vec![Key(K::Left), Pad(P::DPadLeft)]),
+            (MenuRight, vec![Key(K::Right), Pad(P::DPadRight)]),
+            (PageUp, vec![Key(K::PageUp), Pad(P::LeftTrigger)]),
+            (PageDown, vec![Key(K::PageDown), Pad(P::RightTrigger)]),
+            // not clicking, that's walking, and the click that ends a walk mustn't skip a line
+            (Confirm, vec![Key(K::E), Key(K::Return), Pad(P::South)]),
+            (Cancel, vec![Key(K::Back), Pad(P::East)]),
+            (Pause, vec![Key(K::M), Pad(P::North), Pad(P::Start)]),
+            (MainMenu, vec![Key(K::Tab), Pad(P::Select)]),
+            (Settings, vec![Key(K::R), Pad(P::West)]),
+            (History, vec![Key(K::H)]),
+            (Fullscreen, vec![Key(K::F)]),
+            (ScaleUp, vec![Key(K::Equals)]),
+            (ScaleDown, vec![Key(K::Minus)]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl InputMap {
+    // nothing bound to anything
+    pub fn empty() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        match self.bindings.get(&action) {
+            Some(bindings) => bindings,
+            None => &[],
+        }
+    }
+
+    // what the player is told to press
+    pub fn label(&self, action: Action) -> String {
+        match self.bindings(action).first() {
+            Some(binding) => binding.to_string(),
+            None => "?".into(),
+        }
+    }
+
+    // another action the binding would clash with
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(&other, bindings)| {
+                other != action
+                    && other.context().overlaps(action.context())
+                    && bindings.contains(&binding)
+            })
+            .map(|(&other, _)| other)
+    }
+
+    fn check(&self, action: Action, binding: Binding) -> Result<(), BindingConflict> {
+        match self.conflict(action, binding) {
+            Some(other) => Err(BindingConflict {
+                binding,
+                action: other,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // one more way to take the action
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<(), BindingConflict> {
+        self.check(action, binding)?;
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    // replaces the binding shown to the player, the others stay
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), BindingConflict> {
+        self.check(action, binding)?;
+        if let Some(linked) = action.linked() {
+            self.check(linked, binding)?;
+        }
+        for action in std::iter::once(action).chain(action.linked()) {
+            let bindings = self.bindings.entry(action).or_default();
+            bindings.retain(|&other| other != binding);
+            match bindings.first_mut() {
+                Some(first) => *first = binding,
+                None => bindings.push(binding),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: Binding) -> bool {
+        let bindings = match self.bindings.get_mut(&action) {
+            Some(bindings) => bindings,
+            None => return false,
+        };
+        let before = bindings.len();
+        bindings.retain(|&other| other != binding);
+        bindings.len() != before
+    }
+
+    // every binding that clashes with another, each pair once
+    pub fn conflicts(&self) -> Vec<(Action, Action, Binding)> {
+        let mut found = vec![];
+        for (&action, bindings) in &self.bindings {
+            for &binding in bindings {
+                for (&other, other_bindings) in self.bindings.range(action..).skip(1) {
+                    if other.context().overlaps(action.context())
+                        && other_bindings.contains(&binding)
+                    {
+                        found.push((action, other, binding));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+// how far the stick has to be pushed to count, from 0 to 1
+#[derive(Resource, Debug, Clone)]
+pub struct StickSettings {
+    pub deadzone: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self { deadzone: 0.2 }
+    }
+}
+
+// Radial deadzone, past it the stick goes from 0 again up to 1 at full tilt,
+// so that pushing it a bit further than the deadzone is moving slowly.
+pub fn stick_vector(raw: Vec2, deadzone: f32) -> Vec2 {
+    let length = raw.length();
+    if length <= deadzone || length == 0. {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - deadzone) / (1. - deadzone)).min(1.);
+    raw / length * scaled
+}
+
+// button values from this far pressed
+const BUTTON_PRESSED: f32 = 0.5;
+
+// Connected gamepads with their buttons and left sticks.
+#[derive(Resource, Debug, Default)]
+pub struct Controllers {
+    sticks: HashMap<Gamepad, Vec2>,
+    buttons: Input<GamepadButton>,
+}
+
+impl Controllers {
+    pub fn is_connected(&self, gamepad: Gamepad) -> bool {
+        self.sticks.contains_key(&gamepad)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sticks.is_empty()
+    }
+
+    pub fn buttons(&self) -> &Input<GamepadButton> {
+        &self.buttons
+    }
+
+    // the left stick pushed the furthest of all gamepads, before the deadzone
+    pub fn stick(&self) -> Vec2 {
+        self.sticks
+            .values()
+            .copied()
+            .fold(Vec2::ZERO, |furthest, stick| {
+                if stick.length_squared() > furthest.length_squared() {
+                    stick
+                } else {
+                    furthest
+                }
+            })
+    }
+
+    // at the start of every frame, before the events of the frame
+    pub fn clear(&mut self) {
+        self.buttons.clear();
+    }
+
+    pub fn handle(&mut self, event: &GamepadEvent) {
+        let gamepad = event.gamepad;
+        match &event.event_type {
+            GamepadEventType::Connected(_) => {
+                self.sticks.insert(gamepad, Vec2::ZERO);
+            }
+            // nothing stays held on a gamepad that's gone
+            GamepadEventType::Disconnected => {
+                self.sticks.remove(&gamepad);
+                let held: Vec<GamepadButton> = self
+                    .buttons
+                    .get_pressed()
+                    .filter(|button| button.gamepad == gamepad)
+                    .copied()
+                    .collect();
+                for button in held {
+                    self.buttons.release(button);
+                }
+            }
+            GamepadEventType::ButtonChanged(button_type, value) => {
+                if !self.is_connected(gamepad) {
+                    return;
+                }
+                let button = GamepadButton::new(gamepad, *button_type);
+                if *value >= BUTTON_PRESSED {
+                    if !self.buttons.pressed(button) {
+                        self.buttons.press(button);
+                    }
+                } else {
+                    self.buttons.release(button);
+                }
+            }
+            GamepadEventType::AxisChanged(axis_type, value) => {
+                let stick = match self.sticks.get_mut(&gamepad) {
+                    Some(stick) => stick,
+                    None => return,
+                };
+                match axis_type {
+                    GamepadAxisType::LeftStickX => stick.x = *value,
+                    GamepadAxisType::LeftStickY => stick.y = *value,
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+// Presses and releases actions after the state of everything bound to them.
+// A tap shorter than a frame still counts as a press.
+pub fn update_actions(
+    map: &InputMap,
+    actions: &mut Input<Action>,
+    keys: &Input<KeyCode>,
+    mouse: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+) {
+    actions.clear();
+    for action in Action::ALL {
+        let (mut held, mut tapped) = (false, false);
+        for &binding in map.bindings(action) {
+            let (pressed, just_pressed) = match binding {
+                Binding::Key(key) => (keys.pressed(key), keys.just_pressed(key)),
+                Binding::Mouse(button) => (mouse.pressed(button), mouse.just_pressed(button)),
+                Binding::Gamepad(button_type) => (
+                    gamepad_buttons
+                        .get_pressed()
+                        .any(|button| button.button_type == button_type),
+                    gamepad_buttons
+                        .get_just_pressed()
+                        .any(|button| button.button_type == button_type),
+                ),
+            };
+            held |= pressed;
+            tapped |= just_pressed;
+        }
+
+        if (held || tapped) && !actions.pressed(action) {
+            actions.press(action);
+        }
+        if !held && actions.pressed(action) {
+            actions.release(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::gamepad::Gamepad;
+
+    #[test]
+    fn test_defaults_dont_conflict() {
+        assert_eq!(InputMap::default().conflicts(), []);
+        // each listed once
+        let mut all = Action::ALL.to_vec();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), Action::ALL.len());
+    }
+
+    #[test]
+    fn test_rebinding() {
+        let mut map = InputMap::default();
+
+        // shared across contexts that never happen at once
+        assert_eq!(
+            map.conflict(Action::Confirm, Binding::Key(KeyCode::E)),
+            None
+        );
+        assert_eq!(
+            map.bind(Action::Interact, Binding::Key(KeyCode::M)),
+            Err(BindingConflict {
+                binding: Binding::Key(KeyCode::M),
+                action: Action::Pause,
+            })
+        );
+        assert_eq!(
+            map.rebind(Action::MoveUp, Binding::Key(KeyCode::A))
+                .unwrap_err()
+                .to_string(),
+            "A is already bound to MoveLeft"
+        );
+        // nothing changed by the refusals
+        assert_eq!(map, InputMap::default());
+
+        map.rebind(Action::Interact, Binding::Key(KeyCode::Space))
+            .unwrap();
+        assert_eq!(map.label(Action::Interact), "Space");
+        assert_eq!(
+            map.bindings(Action::Interact),
+            [
+                Binding::Key(KeyCode::Space),
+                Binding::Key(KeyCode::Return),
+                Binding::Gamepad(GamepadButtonType::South)
+            ]
+        );
+        // whatever talks to someone goes on with the conversation too
+        assert_eq!(map.label(Action::Confirm), "Space");
+        // but not the other way around
+        map.rebind(Action::Confirm, Binding::Key(KeyCode::Q))
+            .unwrap();
+        assert_eq!(map.label(Action::Interact), "Space");
+
+        // frees the key for something else
+        assert!(map.unbind(Action::History, Binding::Key(KeyCode::H)));
+        assert!(!map.unbind(Action::History, Binding::Key(KeyCode::H)));
+        map.bind(Action::Interact, Binding::Key(KeyCode::H))
+            .unwrap();
+        assert_eq!(map.label(Action::History), "?");
+        // the pad still pauses with the key gone
+        assert!(map.unbind(Action::Pause, Binding::Key(KeyCode::M)));
+        assert_eq!(map.label(Action::Pause), "Y");
+        assert_eq!(map.conflicts(), []);
+    }
+
+    #[test]
+    fn test_update_actions() {
+        let mut map = InputMap::empty();
+        map.bind(Action::Interact, Binding::Key(KeyCode::E))
+            .unwrap();
+        map.bind(Action::Interact, Binding::Mouse(MouseButton::Left))
+            .unwrap();
+        map.bind(Action::Pause, Binding::Gamepad(GamepadButtonType::Start))
+            .unwrap();
+
+        let mut actions = Input::<Action>::default();
+        let mut keys = Input::<KeyCode>::default();
+        let mut mouse = Input::<MouseButton>::default();
+        let mut gamepad_buttons = Input::<GamepadButton>::default();
+        let mut frame = |keys: &mut Input<KeyCode>,
+                         mouse: &mut Input<MouseButton>,
+                         gamepad_buttons: &mut Input<GamepadButton>| {
+            update_actions(&map, &mut actions, keys, mouse, gamepad_buttons);
+            keys.clear();
+            mouse.clear();
+            gamepad_buttons.clear();
+            (
+                actions.just_pressed(Action::Interact),
+                actions.pressed(Action::Interact),
+                actions.just_pressed(Action::Pause),
+            )
+        };
+
+        keys.press(KeyCode::E);
+        assert_eq!(
+            frame(&mut keys, &mut mouse, &mut gamepad_buttons),
+            (true, true, false)
+        );
+        // held with either
+        mouse.press(MouseButton::Left);
+        keys.release(KeyCode::E);
+        assert_eq!(
+            frame(&mut keys, &mut mouse, &mut gamepad_buttons),
+            (false, true, false)
+        );
+        mouse.release(MouseButton::Left);
+        assert_eq!(
+            frame(&mut keys, &mut mouse, &mut gamepad_buttons),
+            (false, false, false)
+        );
+
+        // tapped within a frame
+        keys.press(KeyCode::E);
+        keys.release(KeyCode::E);
+        assert_eq!(
+            frame(&mut keys, &mut mouse, &mut gamepad_buttons),
+            (true, false, false)
+        );
+
+        // any gamepad
+        gamepad_buttons.press(GamepadButton::new(
+            Gamepad::new(1),
+            GamepadButtonType::Start,
+        ));
+        assert_eq!(
+            frame(&mut keys, &mut mouse, &mut gamepad_buttons),
+            (false, false, true)
+        );
+    }
+
+    #[test]
+    fn test_stick_deadzone() {
+        assert_eq!(stick_vector(Vec2::new(0.1, 0.1), 0.2), Vec2::ZERO);
+        assert_eq!(stick_vector(Vec2::ZERO, 0.), Vec2::ZERO);
+        // halfway between the deadzone and full tilt is half speed
+        let half = stick_vector(Vec2::new(0., -0.6), 0.2);
+        assert!((half - Vec2::new(0., -0.5)).length() < 1e-5);
+        // corners of a square stick range aren't faster
+        assert!((stick_vector(Vec2::new(1., 1.), 0.2).length() - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_controllers_hot_plug() {
+        let pad = Gamepad::new(0);
+        let event = |event_type| GamepadEvent::new(pad, event_type);
+        let mut controllers = Controllers::default();
+
+        // not plugged in yet
+        controllers.handle(&event(GamepadEventType::AxisChanged(
+            GamepadAxisType::LeftStickX,
+            1.,
+        )));
+        assert_eq!(controllers.stick(), Vec2::ZERO);
+
+        controllers.handle(&event(GamepadEventType::Connected(
+            bevy::input::gamepad::GamepadInfo { name: "Pad".into() },
+        )));
+        controllers.handle(&event(GamepadEventType::AxisChanged(
+            GamepadAxisType::LeftStickY,
+            -0.5,
+        )));
+        controllers.handle(&event(GamepadEventType::ButtonChanged(
+            GamepadButtonType::South,
+            1.,
+        )));
+        assert_eq!(controllers.stick(), Vec2::new(0., -0.5));
+        let south = GamepadButton::new(pad, GamepadButtonType::South);
+        assert!(controllers.buttons().just_pressed(south));
+
+        controllers.clear();
+        assert!(controllers.buttons().pressed(south));
+        assert!(!controllers.buttons().just_pressed(south));
+
+        // unplugged while held
+        controllers.handle(&event(GamepadEventType::Disconnected));
+        assert!(controllers.is_empty());
+        assert!(!controllers.buttons().pressed(south));
+        assert_eq!(controllers.stick(), Vec2::ZERO);
+    }
+}
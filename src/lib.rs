@@ -0,0 +1,20 @@
+// Game logic that does not depend on the ECS world of the `mistery` binary.
+// Lives in a library so that content tools under src/bin can share it.
+
+pub mod bark;
+pub mod collision;
+pub mod dialog;
+pub mod dot;
+pub mod flags;
+pub mod input;
+pub mod movement;
+pub mod navigation;
+pub mod npcs;
+pub mod proximity;
+pub mod shape;
+pub mod spatial;
+pub mod text_layout;
+pub mod timestep;
+pub mod transcript;
+pub mod typewriter;
+pub mod validate;
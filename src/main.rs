@@ -13,11 +13,6 @@
 //
 // I had greater hopes, but failing fast is a good thing nonetheless
 
-
-
-// NOTE current implementation of NPC proximity does not take into
-//     account lengths when several objects are considered to be in proximity
-//     now, the "closest object" is the latest object detected to be in proximity
 // OPINION it's not worth playing with entity visibility or despawning
 //     when you can just hide them under main menu canvas, will see
 // NOTE you cannot trigger state "enter" using pop(), but can using set(state)
@@ -31,6 +26,8 @@
 //      trying to spawn SpriteBundle (for ex.), with TextBundle it does not
 
 #![allow(dead_code, unused_imports)]
+// systems take whatever they need as arguments
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
 use bevy::ecs::event::ManualEventReader;
 use bevy::ecs::schedule::ShouldRun;
 use bevy::prelude::*;
@@ -39,14 +36,31 @@ use bevy::utils::{HashMap, HashSet};
 use bevy::window::PresentMode;
 use bevy::window::WindowResized;
 use float_to_int::*;
-use std::borrow::BorrowMut;
-use std::f32::consts::{PI, SQRT_2};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::time::Duration;
 use Val as FlexVal;
 use Val::{Percent, Px};
 
 mod unused_systems;
 use crate::unused_systems::*;
 
+use mistery::bark::*;
+use mistery::collision::*;
+use mistery::dialog::*;
+use mistery::flags::*;
+use mistery::input::*;
+use mistery::movement::*;
+use mistery::navigation::*;
+use mistery::npcs::*;
+use mistery::proximity::*;
+use mistery::shape::*;
+use mistery::spatial::*;
+use mistery::text_layout::*;
+use mistery::timestep::*;
+use mistery::transcript::*;
+use mistery::typewriter::*;
+
 const PACKAGE_NAME: &'static str = "mistery";
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -56,8 +70,13 @@ enum AppState {
     PauseScreen,
     Settings,
     DialogWindow,
+    History,
 }
 
+// gameplay, in ticks of the same length however long frames take
+#[derive(StageLabel)]
+struct GameplayStage;
+
 #[derive(SystemLabel)]
 enum Label {
     SetupCamera,
@@ -66,6 +85,11 @@ enum Label {
     AwayFromNPCEventHandler,
     NextToObjectWatcher,
     SpawnNPCs,
+    Interact,
+    // pausing, opening menus and leaving them
+    StateTriggers,
+    TickStart,
+    Gameplay,
 }
 
 #[derive(Debug, PartialEq)]
@@ -144,13 +168,52 @@ fn main() {
         .insert_resource(ClearColor(Color::DARK_GRAY))
         .add_startup_system(set_up_camera)
         .add_startup_system(init_screen_resolution)
+        .add_startup_system(load_dialogs)
+        .add_startup_system(load_game_flags)
+        .add_startup_system(load_movement_settings)
+        .add_startup_system(load_dialog_font)
         // .insert_resource(CurrentScreenResolution {value: Some(screen_resolution)})
         .insert_resource(CurrentScreenResolution::default())
         .insert_resource(ProximityToObjResource::default())
-        .insert_resource(NearestNPCinProximity::default())
+        .insert_resource(ProximityGrid::default())
+        .insert_resource(InteractionTarget::default())
+        .insert_resource(ActiveDialog::default())
+        .insert_resource(TypewriterSettings::default())
+        .insert_resource(DialogLayout::default())
+        .insert_resource(Transcript::default())
+        .insert_resource(PlayTime::default())
+        .insert_resource(HistoryView::default())
+        .insert_resource(Inventory::default())
+        .insert_resource(BarkSettings::default())
+        .insert_resource(BarkCooldowns::default())
+        .insert_resource(InputMap::default())
+        .insert_resource(Input::<Action>::default())
+        .insert_resource(Controllers::default())
+        .insert_resource(StickSettings::default())
+        .add_plugin(GameplayTicksPlugin)
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            read_gamepads.after(bevy::input::InputSystem),
+        )
+        // keys and buttons into actions, before any system reads them
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            read_actions
+                .after(bevy::input::InputSystem)
+                .after(read_gamepads),
+        )
         .add_event::<NextToObjEvent>()
         .add_event::<AwayFromObjEvent>()
+        .add_event::<TargetChanged>()
+        .add_event::<InteractEvent>()
+        .add_event::<MoveToEvent>()
+        .insert_resource(NavigationGrid::default())
         .add_system(window_scaling)
+        // Keeps up with despawns in any state, every frame as removals don't last until a tick.
+        // After Update, as despawns by its commands only get applied at its end.
+        .add_system_to_stage(CoreStage::PostUpdate, index_proximity_objects)
+        // the same, for colliders removed by commands like opening a door does
+        .add_system_to_stage(CoreStage::PostUpdate, update_navigation_grid)
         .add_system(away_from_npc_event_handler.label(Label::AwayFromNPCEventHandler))
         .add_system(next_to_npc_event_handler.after(Label::AwayFromNPCEventHandler))
         // .add_state(AppState::MainMenu)
@@ -165,34 +228,95 @@ fn main() {
                 .with_system(spawn_npcs)
                 .label(Label::SpawnNPCs),
         )
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_props))
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_walls))
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_interaction_prompt))
+        .add_system_set_to_stage(
+            GameplayStage,
+            SystemSet::new()
+                .with_run_criteria(in_game)
+                .label(Label::Gameplay)
+                .after(Label::TickStart)
+                // move player only when InGame
+                .with_system(player_movement.before(next_to_obj_watcher))
+                .with_system(next_to_obj_watcher.before(rank_interactables_in_proximity))
+                .with_system(rank_interactables_in_proximity),
+        )
         .add_system_set(
             SystemSet::on_update(AppState::InGame)
-                .with_system(next_to_obj_watcher)
-                // move player only when InGame
-                .with_system(player_movement),
+                .with_system(update_interaction_prompt)
+                .with_system(input_move_to_trigger.before(move_to_handler))
+                .with_system(move_to_handler)
+                .with_system(tick_play_time)
+                .with_system(spawn_barks)
+                .with_system(fade_out_barks.before(fade_barks))
+                .with_system(fade_barks),
         )
         .add_system_set(
             SystemSet::on_exit(AppState::InGame)
                 .with_system(despawn_all::<LevelUnload>)
                 .with_system(reset_resource::<ProximityToObjResource>)
-                .with_system(reset_resource::<NearestNPCinProximity>),
+                .with_system(reset_resource::<ProximityGrid>)
+                .with_system(reset_resource::<InteractionTarget>)
+                .with_system(reset_resource::<NavigationGrid>),
+        )
+        .add_system(input_pause_screen_trigger.label(Label::StateTriggers))
+        .add_system_set(
+            SystemSet::on_enter(AppState::PauseScreen)
+                .with_system(setup_pause_screen)
+                .with_system(hide_all::<InteractionPrompt>),
         )
-        .add_system(keyboard_pause_screen_trigger)
-        .add_system_set(SystemSet::on_enter(AppState::PauseScreen).with_system(setup_pause_screen))
         .add_system_set(
             SystemSet::on_exit(AppState::PauseScreen).with_system(despawn_all::<PauseScreen>),
         )
-        .add_system(keyboard_dialog_window_trigger)
+        .add_system(input_interact_trigger.label(Label::Interact))
+        .add_system(route_interaction.label(Label::Interact))
+        // a handler per kind of interaction
+        // after anything else changing the state this frame, as it yields to them
+        .add_system(
+            talk_handler
+                .after(Label::Interact)
+                .after(Label::StateTriggers),
+        )
+        .add_system(examine_handler.after(Label::Interact))
+        .add_system(pick_up_handler.after(Label::Interact))
+        .add_system(open_handler.after(Label::Interact))
+        .add_system(use_handler.after(Label::Interact))
+        .add_system(input_dialog_window_trigger)
+        .add_system_set(
+            SystemSet::on_enter(AppState::DialogWindow)
+                .with_system(setup_dialog_window)
+                .with_system(despawn_all::<BarkBubble>)
+                .with_system(hide_all::<InteractionPrompt>),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::DialogWindow)
+                .with_system(lay_out_dialog.before(dialog_typewriter))
+                // runs only while the window is on top, so pausing freezes the reveal
+                .with_system(dialog_typewriter.before(update_dialog_text))
+                .with_system(update_dialog_text)
+                .with_system(update_dialog_portrait)
+                .with_system(place_dialog_window)
+                .with_system(tick_play_time),
+        )
         .add_system_set(
-            SystemSet::on_enter(AppState::DialogWindow).with_system(setup_dialog_window),
+            SystemSet::on_exit(AppState::DialogWindow)
+                .with_system(despawn_all::<DialogWindow>)
+                .with_system(reset_resource::<ActiveDialog>),
         )
+        .add_system(input_history_trigger.label(Label::StateTriggers))
+        .add_system_set(SystemSet::on_enter(AppState::History).with_system(setup_history))
         .add_system_set(
-            SystemSet::on_exit(AppState::DialogWindow).with_system(despawn_all::<DialogWindow>),
+            SystemSet::on_update(AppState::History)
+                .with_system(history_navigation)
+                .with_system(update_history.after(history_navigation)),
         )
-        .add_system(keyboard_main_menu_trigger)
+        .add_system_set(SystemSet::on_exit(AppState::History).with_system(despawn_all::<History>))
+        .add_system(input_main_menu_trigger.label(Label::StateTriggers))
         .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(setup_main_menu))
         .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(despawn_all::<MainMenu>))
-        .add_system(keyboard_settings_trigger)
+        .add_system(input_settings_trigger.label(Label::StateTriggers))
+        .add_system(input_back_trigger.label(Label::StateTriggers))
         .add_system_set(SystemSet::on_enter(AppState::Settings).with_system(setup_settings))
         .add_system_set(SystemSet::on_exit(AppState::Settings).with_system(despawn_all::<Settings>))
         // .add_plugin(bevy::diagnostic::LogDiagnosticsPlugin::default())
@@ -244,15 +368,29 @@ struct PlayerBundle {
     name: Name,
     model: MaterialMesh2dBundle<ColorMaterial>,
     // model: SpriteBundle,
+    shape: ProximityShape,
+    observer: ProximityObserver,
+    body: KinematicBody,
+    velocity: Velocity,
+    interpolated: Interpolated,
+    facing: Facing,
     _identity: Player,
     _unload: LevelUnload,
 }
 
 impl PlayerBundle {
-    fn new(model: MaterialMesh2dBundle<ColorMaterial>) -> Self {
+    fn new(model: MaterialMesh2dBundle<ColorMaterial>, shape: ProximityShape) -> Self {
         Self {
             name: "Player".into(),
             model: model,
+            body: KinematicBody {
+                shape: shape.value.clone(),
+            },
+            shape,
+            observer: ProximityObserver,
+            velocity: Velocity::default(),
+            interpolated: Interpolated::default(),
+            facing: Facing::default(),
             _unload: LevelUnload,
             _identity: Player,
             // model: SpriteBundle {
@@ -267,18 +405,260 @@ impl PlayerBundle {
     }
 }
 
+// direction the player last moved in
+#[derive(Component)]
+struct Facing {
+    value: Vec2,
+}
+
+impl Default for Facing {
+    // towards the camera
+    fn default() -> Self {
+        Self { value: Vec2::NEG_Y }
+    }
+}
+
 #[derive(Component)]
 struct NPC;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum InteractionKind {
+    Talk,
+    Examine,
+    PickUp,
+    Open,
+    Use,
+}
+
+impl InteractionKind {
+    fn verb(self) -> &'static str {
+        use InteractionKind::*;
+        match self {
+            Talk => "Talk to",
+            Examine => "Examine",
+            PickUp => "Pick up",
+            Open => "Open",
+            Use => "Use",
+        }
+    }
+}
+
+// something the player can do something with, once it's the nearest one in proximity
+#[derive(Component)]
+struct Interactable {
+    kind: InteractionKind,
+    // applied on every interaction, whatever the kind
+    effects: Vec<Effect>,
+}
+
+impl Interactable {
+    fn new(kind: InteractionKind) -> Self {
+        Self {
+            kind,
+            effects: vec![],
+        }
+    }
+}
+
+// interactable only while the player faces it, within `half_angle` radians either side
+#[derive(Component)]
+struct FacingCone {
+    half_angle: f32,
+}
+
+// blocks the line of sight, nothing behind it can be interacted with
+#[derive(Component)]
+struct Occluder {
+    shape: Shape,
+}
+
+// stays in place and stops whatever moves into it
+#[derive(Component)]
+struct Collider {
+    shape: Shape,
+}
+
+impl Collider {
+    fn from_sprite(sprite: &Sprite) -> Self {
+        Self {
+            shape: ProximityShape::from_sprite(sprite).value,
+        }
+    }
+}
+
+// moved by systems, stopping at colliders and sliding along them
+#[derive(Component)]
+struct KinematicBody {
+    shape: Shape,
+}
+
+// units per second
+#[derive(Component, Debug, Default)]
+struct Velocity {
+    value: Vec2,
+}
+
+// where the player walks on its own after a click, and who to talk to once there
+#[derive(Component, Debug)]
+struct Route {
+    waypoints: VecDeque<Vec2>,
+    interact_with: Option<Entity>,
+    // how long to wait at the end for them to become the target
+    patience: Duration,
+}
+
+// Moved by gameplay ticks, drawn between where the last two of them left it.
+// Outside of drawing, its transform is where the last tick left it.
+#[derive(Component, Debug, Default)]
+struct Interpolated {
+    previous: Vec2,
+    current: Vec2,
+}
+
+// Runs GameplayStage in ticks, systems in it go after Label::TickStart
+// and get labeled with Label::Gameplay.
+struct GameplayTicksPlugin;
+
+impl Plugin for GameplayTicksPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Timestep::default())
+            .add_system_to_stage(CoreStage::PreUpdate, advance_timestep)
+            .add_system_to_stage(CoreStage::PreUpdate, snap_to_ticked_positions)
+            .add_stage_after(
+                CoreStage::PreUpdate,
+                GameplayStage,
+                SystemStage::parallel().with_run_criteria(gameplay_tick),
+            )
+            .add_system_to_stage(GameplayStage, start_tick.label(Label::TickStart))
+            .add_system_to_stage(GameplayStage, end_tick.after(Label::Gameplay))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate_positions.before(bevy::transform::TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn advance_timestep(time: Res<Time>, mut timestep: ResMut<Timestep>) {
+    timestep.advance(time.delta());
+}
+
+// runs the gameplay stage once per tick due
+fn gameplay_tick(mut timestep: ResMut<Timestep>) -> ShouldRun {
+    if timestep.tick() {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn snap_to_ticked_positions(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in &mut query {
+        transform.translation.x = interpolated.current.x;
+        transform.translation.y = interpolated.current.y;
+    }
+}
+
+fn start_tick(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in &mut query {
+        interpolated.previous = transform.translation.truncate();
+    }
+}
+
+fn end_tick(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in &mut query {
+        interpolated.current = transform.translation.truncate();
+    }
+}
+
+fn interpolate_positions(
+    timestep: Res<Timestep>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    for (mut transform, mut interpolated) in &mut query {
+        let position = transform.translation.truncate();
+        // spawned, or put somewhere else in between ticks
+        if position != interpolated.current {
+            interpolated.previous = position;
+            interpolated.current = position;
+            continue;
+        }
+        let drawn_at = interpolated
+            .previous
+            .lerp(interpolated.current, timestep.overstep());
+        transform.translation.x = drawn_at.x;
+        transform.translation.y = drawn_at.y;
+    }
+}
+
+// what the player learns examining an object
+#[derive(Component)]
+struct Description {
+    value: String,
+}
+
+#[derive(Component, Default)]
+struct Openable {
+    open: bool,
+}
+
+// names of whatever got picked up
+#[derive(Resource, Default)]
+struct Inventory {
+    items: Vec<String>,
+}
+
+// an interaction, handled by the systems of its kind
+struct InteractEvent {
+    entity: Entity,
+    kind: InteractionKind,
+}
+
+// notices objects with InProximity coming next to it and going away
+#[derive(Component)]
+struct ProximityObserver;
+
+// distances are between centers, unless the object has a ProximityShape
 #[derive(Component)]
 struct InProximity {
-    edge_distance: f32,
+    range: ProximityRange,
+}
+
+// outline to measure proximity from, instead of the center
+#[derive(Component, Debug, Clone)]
+struct ProximityShape {
+    value: Shape,
+}
+
+impl ProximityShape {
+    fn from_sprite(sprite: &Sprite) -> Self {
+        Self {
+            value: match sprite.custom_size {
+                Some(size) => Shape::rect(size),
+                None => Shape::Point,
+            },
+        }
+    }
+}
+
+impl From<&shape::Circle> for ProximityShape {
+    fn from(circle: &shape::Circle) -> Self {
+        Self {
+            value: Shape::Circle {
+                radius: circle.radius,
+            },
+        }
+    }
 }
 
 #[derive(Bundle)]
 struct NPCBundle {
     name: Name,
     in_proximity: InProximity,
+    shape: ProximityShape,
+    interactable: Interactable,
+    // no talking to someone's back
+    facing_cone: FacingCone,
+    collider: Collider,
     model: SpriteBundle,
 
     _identity: NPC,
@@ -297,21 +677,113 @@ impl TransformFromXY for Transform {
 
 impl NPCBundle {
     fn new(name: impl Into<Name>, transform: Transform) -> Self {
+        let sprite = Sprite {
+            color: Color::rgb(0.25, 0.25, 0.75),
+            custom_size: Some(Vec2::new(100., 100.)),
+            ..default()
+        };
+
+        Self {
+            name: name.into(),
+            // gaps between the player and the sprite
+            in_proximity: InProximity {
+                range: ProximityRange {
+                    enter: 50.,
+                    exit: 75.,
+                    dwell: Duration::ZERO,
+                },
+            },
+            shape: ProximityShape::from_sprite(&sprite),
+            interactable: Interactable::new(InteractionKind::Talk),
+            facing_cone: FacingCone {
+                half_angle: PI / 3.,
+            },
+            collider: Collider::from_sprite(&sprite),
+            model: SpriteBundle {
+                sprite,
+                transform,
+                ..default()
+            },
+            _identity: NPC,
+            _unload: LevelUnload,
+        }
+    }
+}
+
+// anything in the level, other than characters
+#[derive(Bundle)]
+struct PropBundle {
+    name: Name,
+    in_proximity: InProximity,
+    shape: ProximityShape,
+    interactable: Interactable,
+    model: SpriteBundle,
+
+    _unload: LevelUnload,
+}
+
+impl PropBundle {
+    fn new(
+        name: impl Into<Name>,
+        interactable: Interactable,
+        size: Vec2,
+        color: Color,
+        transform: Transform,
+    ) -> Self {
+        let sprite = Sprite {
+            color,
+            custom_size: Some(size),
+            ..default()
+        };
+
         Self {
             name: name.into(),
             in_proximity: InProximity {
-                edge_distance: 150.,
+                range: ProximityRange {
+                    enter: 30.,
+                    exit: 45.,
+                    dwell: Duration::ZERO,
+                },
+            },
+            shape: ProximityShape::from_sprite(&sprite),
+            interactable,
+            model: SpriteBundle {
+                sprite,
+                transform,
+                ..default()
+            },
+            _unload: LevelUnload,
+        }
+    }
+}
+
+#[derive(Bundle)]
+struct WallBundle {
+    occluder: Occluder,
+    collider: Collider,
+    model: SpriteBundle,
+
+    _unload: LevelUnload,
+}
+
+impl WallBundle {
+    fn new(size: Vec2, transform: Transform) -> Self {
+        Self {
+            occluder: Occluder {
+                shape: Shape::rect(size),
+            },
+            collider: Collider {
+                shape: Shape::rect(size),
             },
             model: SpriteBundle {
                 sprite: Sprite {
-                    color: Color::rgb(0.25, 0.25, 0.75),
-                    custom_size: Some(Vec2::new(100., 100.)),
+                    color: Color::BLACK,
+                    custom_size: Some(size),
                     ..default()
                 },
                 transform,
                 ..default()
             },
-            _identity: NPC,
             _unload: LevelUnload,
         }
     }
@@ -326,12 +798,15 @@ fn spawn_player(
     let shape = shape::Circle::new(50.);
     let material = ColorMaterial::from(Color::BEIGE);
 
-    commands.spawn(PlayerBundle::new(MaterialMesh2dBundle {
-        mesh: meshes.add(shape.into()).into(),
-        material: materials.add(material),
-        // transform: Transform::from_translation(Vec3::ZERO),
-        ..default()
-    }));
+    commands.spawn(PlayerBundle::new(
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape.into()).into(),
+            material: materials.add(material),
+            // transform: Transform::from_translation(Vec3::ZERO),
+            ..default()
+        },
+        ProximityShape::from(&shape),
+    ));
     debug!("Spawning a player");
 }
 
@@ -343,6 +818,11 @@ fn spawned<T: Component>(components: Query<With<T>>) -> ShouldRun {
     (!components.is_empty()).into()
 }
 
+// SystemSet::on_update only works in the stage the state is driven in, CoreStage::Update
+fn in_game(state: Res<State<AppState>>) -> ShouldRun {
+    (state.current() == &AppState::InGame).into()
+}
+
 fn despawn_all<T: Component>(mut commands: Commands, q: Query<Entity, With<T>>) {
     let type_name = {
         let fully_qualified_type_name = std::any::type_name::<T>();
@@ -370,84 +850,237 @@ fn show_all<T: Component>(mut components: Query<&mut Visibility, With<T>>) {
     }
 }
 
-fn spawn_npcs(mut commands: Commands) {
-    commands.spawn(NPCBundle::new("Joe", Transform::from_xy(200., 0.)));
-    commands.spawn(NPCBundle::new("Rue", Transform::from_xy(-200., 100.)));
-    commands.spawn(NPCBundle::new("Moe", Transform::from_xy(-350., 100.)));
+fn spawn_npcs(mut commands: Commands, flags: Res<GameFlags>) {
+    for npc in NPCS {
+        let present = match npc.condition {
+            Some(condition) => flags.check(condition).unwrap_or_else(|e| {
+                warn!("spawn condition of {}: {}", npc.name, e);
+                false
+            }),
+            None => true,
+        };
+        if present {
+            let (x, y) = npc.position;
+            commands.spawn(NPCBundle::new(npc.name, Transform::from_xy(x, y)));
+        }
+    }
 
     debug!("Spawning NPC");
 }
 
+fn spawn_props(mut commands: Commands) {
+    let door = PropBundle::new(
+        "Door",
+        Interactable::new(InteractionKind::Open),
+        Vec2::new(40., 120.),
+        Color::MAROON,
+        Transform::from_xy(450., -150.),
+    );
+    // closed to begin with
+    let collider = Collider::from_sprite(&door.model.sprite);
+    commands.spawn((door, Openable::default(), collider));
+    commands.spawn((
+        PropBundle::new(
+            "Note",
+            Interactable::new(InteractionKind::Examine),
+            Vec2::new(30., 20.),
+            Color::ANTIQUE_WHITE,
+            Transform::from_xy(-100., -200.),
+        ),
+        Description {
+            value: "\"Docks, midnight. Come alone. -M\"".into(),
+        },
+    ));
+    commands.spawn(PropBundle::new(
+        "Cufflink",
+        Interactable {
+            kind: InteractionKind::PickUp,
+            effects: vec![Effect::parse("clues += 1").unwrap()],
+        },
+        Vec2::new(16., 16.),
+        Color::GOLD,
+        Transform::from_xy(0., 220.),
+    ));
+
+    debug!("Spawning props");
+}
+
+fn spawn_walls(mut commands: Commands) {
+    // between Rue and Moe
+    commands.spawn(WallBundle::new(
+        Vec2::new(20., 200.),
+        Transform::from_xy(-275., 100.),
+    ));
+
+    debug!("Spawning walls");
+}
+
 fn reset_resource<T: Resource + Default>(mut commands: Commands) {
     commands.insert_resource(T::default());
 }
 
+// for each observer, objects it's next to, or about to come next to or leave,
+// everything else is away and isn't kept
 #[derive(Resource, Default)]
 struct ProximityToObjResource {
-    values: HashMap<Entity, (bool, f32)>,
+    values: HashMap<Entity, HashMap<Entity, ProximityState>>,
+}
+
+impl ProximityToObjResource {
+    fn observed_by(&self, observer: Entity) -> impl Iterator<Item = (Entity, &ProximityState)> {
+        self.values
+            .get(&observer)
+            .into_iter()
+            .flat_map(|observed| observed.iter().map(|(&entity, state)| (entity, state)))
+    }
+}
+
+// where objects with InProximity are, so that only the ones around observers get checked
+#[derive(Resource)]
+struct ProximityGrid {
+    value: SpatialGrid<Entity>,
+    // the largest radius of anything ever indexed, shapes included,
+    // whatever is further than that and the shape of an observer is certainly away
+    reach: f32,
+}
+
+impl Default for ProximityGrid {
+    fn default() -> Self {
+        Self {
+            value: SpatialGrid::new(200.),
+            reach: 0.,
+        }
+    }
 }
 
+fn index_proximity_objects(
+    objects: Query<
+        (Entity, &Transform, &InProximity, Option<&ProximityShape>),
+        Or<(
+            Changed<Transform>,
+            Changed<InProximity>,
+            Changed<ProximityShape>,
+        )>,
+    >,
+    removed: RemovedComponents<InProximity>,
+    mut grid: ResMut<ProximityGrid>,
+) {
+    for entity in removed.iter() {
+        grid.value.remove(entity);
+    }
+    for (entity, transform, in_proximity, shape) in &objects {
+        let range = &in_proximity.range;
+        let extent = shape.map_or(0., |shape| shape.value.bounding_radius());
+        grid.reach = grid.reach.max(range.enter.max(range.exit) + extent);
+        grid.value.insert(entity, transform.translation.truncate());
+    }
+}
+
+// interactables in proximity, nearest first
 #[derive(Resource, Default)]
-struct NearestNPCinProximity {
+struct InteractionTarget {
     value: Vec<Entity>,
 }
 
-impl NearestNPCinProximity {
-    // nearest is considered to be the latest one, joining a stack
+impl InteractionTarget {
     fn get(&self) -> Option<&Entity> {
-        self.value.last()
+        self.value.first()
     }
 
-    // check if there's "any" npc in proximity
+    // check if there's "any" interactable in proximity
     fn any(&self) -> bool {
         !self.value.is_empty()
     }
 }
 
+// `entity` came next to `observer`
 struct NextToObjEvent {
+    observer: Entity,
     entity: Entity,
 }
 struct AwayFromObjEvent {
+    observer: Entity,
     entity: Entity,
 }
+// the nearest interactable in proximity is a different one, or there's none anymore
+struct TargetChanged {
+    previous: Option<Entity>,
+    current: Option<Entity>,
+}
 
 fn next_to_obj_watcher(
-    rel_obj_transforms: Query<(Entity, &Transform, &InProximity)>,
-    player_transform: Query<&Transform, With<Player>>,
+    timestep: Res<Timestep>,
+    grid: Res<ProximityGrid>,
+    rel_obj_transforms: Query<(&Transform, &InProximity, Option<&ProximityShape>)>,
+    observers: Query<(Entity, &Transform, Option<&ProximityShape>), With<ProximityObserver>>,
     mut next_to_obj: ResMut<ProximityToObjResource>,
     mut ev_next_to_obj: EventWriter<NextToObjEvent>,
     mut ev_away_from_obj: EventWriter<AwayFromObjEvent>,
 ) {
-    let player_transform = player_transform.single();
+    // nobody left to tell what a despawned observer was next to
+    next_to_obj
+        .values
+        .retain(|&observer, _| observers.contains(observer));
 
-    for (entity, obj_transform, in_proximity) in &rel_obj_transforms {
-        let next_to = next_to_obj.values.get(&entity);
+    for (observer, observer_transform, observer_shape) in &observers {
+        let observer_position = observer_transform.translation.truncate();
+        let observer_shape = observer_shape.map_or(&Shape::Point, |shape| &shape.value);
+        let observed = next_to_obj.values.entry(observer).or_default();
 
-        let distance_to_object = obj_transform
-            .translation
-            .distance(player_transform.translation);
+        let mut candidates: Vec<Entity> = grid
+            .value
+            .within(
+                observer_position,
+                grid.reach + observer_shape.bounding_radius(),
+            )
+            .into_iter()
+            .map(|(entity, _)| entity)
+            // an NPC that observes doesn't observe itself
+            .filter(|&entity| entity != observer)
+            .collect();
+        // out of reach or gone by now, but they have to find out
+        candidates.extend(observed.keys().copied());
+        // same order of events every time
+        candidates.sort();
+        candidates.dedup();
 
-        if distance_to_object < in_proximity.edge_distance {
-            match next_to {
-                None | Some((false, _)) => {
-                    next_to_obj
-                        .values
-                        .insert(entity, (true, distance_to_object));
-                    ev_next_to_obj.send(NextToObjEvent { entity });
+        for entity in candidates {
+            let (obj_transform, in_proximity, obj_shape) = match rel_obj_transforms.get(entity) {
+                Ok(obj) => obj,
+                // despawned, or not observed anymore
+                Err(_) => {
+                    let was_inside = observed
+                        .remove(&entity)
+                        .is_some_and(|state| state.is_inside());
+                    if was_inside {
+                        ev_away_from_obj.send(AwayFromObjEvent { observer, entity });
+                    }
+                    continue;
                 }
-                Some((true, _)) => (),
+            };
+            let obj_position = obj_transform.translation.truncate();
+            let distance_to_object = match obj_shape {
+                Some(obj_shape) => gap(
+                    observer_shape,
+                    observer_position,
+                    &obj_shape.value,
+                    obj_position,
+                ),
+                None => obj_position.distance(observer_position),
+            };
+
+            let state = observed.entry(entity).or_default();
+            let change = state.update(distance_to_object, timestep.step(), &in_proximity.range);
+            if state.is_idle() {
+                observed.remove(&entity);
             }
-        } else {
-            match next_to {
-                Some((true, _)) => {
-                    next_to_obj
-                        .values
-                        .insert(entity, (false, distance_to_object));
-                    ev_away_from_obj.send(AwayFromObjEvent { entity });
+            match change {
+                Some(ProximityChange::Entered) => {
+                    ev_next_to_obj.send(NextToObjEvent { observer, entity })
+                }
+                Some(ProximityChange::Left) => {
+                    ev_away_from_obj.send(AwayFromObjEvent { observer, entity })
                 }
-                Some((false, _)) => (),
-                // if you never've been close don't send AwayFrom event
-                // not sure if the same should apply to NextTo event
                 None => (),
             }
         }
@@ -457,175 +1090,1653 @@ fn next_to_obj_watcher(
 fn next_to_npc_event_handler(
     mut ev_next_to_obj: EventReader<NextToObjEvent>,
     npcs: Query<(Entity, &Name), With<NPC>>,
-    mut nearest_npc_in_proximity: ResMut<NearestNPCinProximity>,
 ) {
     for ev in ev_next_to_obj.iter() {
-        let entity = ev.entity;
-        let name = npcs.get_component::<Name>(entity).unwrap();
-
-        nearest_npc_in_proximity.value.push(entity);
-
-        debug!("Next to NPC {}", name.value);
+        // anything else may be in proximity as well
+        if let Ok(name) = npcs.get_component::<Name>(ev.entity) {
+            debug!("{:?} next to NPC {}", ev.observer, name.value);
+        }
     }
 }
 
 fn away_from_npc_event_handler(
     mut ev_away_from_obj: EventReader<AwayFromObjEvent>,
     npcs: Query<(Entity, &Name), With<NPC>>,
-    mut nearest_npc_in_proximity: ResMut<NearestNPCinProximity>,
 ) {
     for ev in ev_away_from_obj.iter() {
-        let entity = ev.entity;
-        let name = npcs.get_component::<Name>(entity).unwrap();
-
-        let idx = nearest_npc_in_proximity
-            .value
-            .iter()
-            .position(|&e| e == entity)
-            .unwrap();
-        nearest_npc_in_proximity.value.remove(idx);
-
-        debug!("Away from NPC {}", name.value);
+        if let Ok(name) = npcs.get_component::<Name>(ev.entity) {
+            debug!("{:?} away from NPC {}", ev.observer, name.value);
+        }
     }
 }
 
-fn player_movement(
-    time: Res<Time>,
-    keys: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, With<Player>>,
-) {
-    let mut transform = query.single_mut();
-
-    let multiplier = 250.;
-    let magnitude = multiplier * time.delta_seconds();
-    let diagonal_magnitude = magnitude / SQRT_2;
-
-    let up = keys.any_pressed([KeyCode::W, KeyCode::Up]);
-    let left = keys.any_pressed([KeyCode::A, KeyCode::Left]);
-    let down = keys.any_pressed([KeyCode::S, KeyCode::Down]);
-    let right = keys.any_pressed([KeyCode::D, KeyCode::Right]);
-
-    // if left {
-    //     transform.rotation = Quat::from_rotation_y(PI);
-    // } else if right {
-    //     transform.rotation = Quat::default();
-    // }
+// whether nothing stands between the points, but the object looked at
+fn in_line_of_sight(
+    from: Vec2,
+    to: Vec2,
+    target: Entity,
+    occluders: &Query<(Entity, &Transform, &Occluder)>,
+) -> bool {
+    !occluders.iter().any(|(entity, transform, occluder)| {
+        entity != target
+            && segment_hits(&occluder.shape, transform.translation.truncate(), from, to)
+    })
+}
 
-    let mut translation = transform.translation.borrow_mut();
+// the player moves every frame, so does the ranking
+fn rank_interactables_in_proximity(
+    next_to_obj: Res<ProximityToObjResource>,
+    player: Query<(Entity, &Transform, &Facing), With<Player>>,
+    interactables: Query<(&Transform, Option<&FacingCone>), With<Interactable>>,
+    occluders: Query<(Entity, &Transform, &Occluder)>,
+    mut interaction_target: ResMut<InteractionTarget>,
+    mut ev_target_changed: EventWriter<TargetChanged>,
+) {
+    // it's the one player that interacts
+    let (player, player_transform, facing) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let player_at = player_transform.translation.truncate();
 
-    if up && left {
-        translation.y += diagonal_magnitude;
-        translation.x -= diagonal_magnitude;
-    }
-    if up && right {
-        translation.y += diagonal_magnitude;
-        translation.x += diagonal_magnitude;
-    }
-    if down && left {
-        translation.y -= diagonal_magnitude;
-        translation.x -= diagonal_magnitude;
-    }
-    if down && right {
-        translation.y -= diagonal_magnitude;
-        translation.x += diagonal_magnitude;
-    }
-    if up && !(left || right) {
-        translation.y += magnitude;
-    }
-    if left && !(up || down) {
-        translation.x -= magnitude;
-    }
-    if down && !(left || right) {
-        translation.y -= magnitude;
+    let in_range: Vec<(Entity, f32)> = next_to_obj
+        .observed_by(player)
+        .filter(|(_, state)| state.is_inside())
+        .filter(|&(entity, _)| {
+            let (transform, cone) = match interactables.get(entity) {
+                Ok(interactable) => interactable,
+                Err(_) => return false,
+            };
+            let at = transform.translation.truncate();
+            let faced = match cone {
+                Some(cone) => in_cone(facing.value, at - player_at, cone.half_angle),
+                None => true,
+            };
+            faced && in_line_of_sight(player_at, at, entity, &occluders)
+        })
+        .map(|(entity, state)| (entity, state.distance()))
+        .collect();
+    let ranked = rank(&in_range, &interaction_target.value);
+    if ranked == interaction_target.value {
+        return;
     }
-    if right && !(up || down) {
-        translation.x += magnitude;
+
+    if target_changed(&interaction_target.value, &ranked) {
+        let previous = nearest(&interaction_target.value);
+        let current = nearest(&ranked);
+        debug!("Target changed from {:?} to {:?}", previous, current);
+        ev_target_changed.send(TargetChanged { previous, current });
     }
+    interaction_target.value = ranked;
 }
 
+// a line floating above an object, made of a background and the text on top of it
 #[derive(Component)]
-struct PauseScreen;
-
-#[derive(Bundle)]
-struct PauseScreenBundle {
-    sprite: SpriteBundle,
-    _ps: PauseScreen,
+struct BarkBubble {
+    // who says it, or what it's about
+    owner: Entity,
+    // set once the player walks away
+    fade: Option<Fade>,
 }
 
-fn setup_pause_screen(mut commands: Commands) {
-    commands.spawn(PauseScreenBundle {
-        sprite: SpriteBundle {
+const BARK_FONT_SIZE: f32 = 20.;
+const BARK_PADDING: f32 = 8.;
+// between the top of an object and the bubble
+const BARK_MARGIN: f32 = 10.;
+
+fn spawn_bark_bubble(
+    commands: &mut Commands,
+    owner: Entity,
+    owner_transform: &Transform,
+    owner_sprite: Option<&Sprite>,
+    line: &str,
+    font: &DialogFont,
+    fonts: &Assets<Font>,
+) {
+    let measure = TextMeasure::new(
+        fonts.get(&font.handle).map(|font| font.font.clone()),
+        BARK_FONT_SIZE,
+    );
+    let size = Vec2::new(measure.width(line), measure.line_height()) + 2. * BARK_PADDING;
+    let owner_height = owner_sprite
+        .and_then(|sprite| sprite.custom_size)
+        .map_or(0., |size| size.y);
+    let x = owner_transform.translation.x;
+    // leaving room for the interaction prompt underneath
+    let y = owner_transform.translation.y
+        + owner_height / 2.
+        + PROMPT_HEIGHT
+        + BARK_MARGIN
+        + size.y / 2.;
+
+    // in front of everything in the level
+    let mut transform = Stacking::InGame.from_xy(x, y);
+    transform.translation.z += 0.5;
+    commands.spawn((
+        SpriteBundle {
             sprite: Sprite {
-                color: Color::SEA_GREEN,
-                custom_size: Some(Vec2::new(100., 100.)),
+                color: Color::WHITE,
+                custom_size: Some(size),
                 ..default()
             },
-            transform: Stacking::PauseScreen.into(),
+            transform,
             ..default()
         },
-        _ps: PauseScreen,
-    });
-}
+        BarkBubble { owner, fade: None },
+        LevelUnload,
+    ));
+
+    transform.translation.z += 0.1;
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                line,
+                TextStyle {
+                    font: font.handle.clone(),
+                    font_size: BARK_FONT_SIZE,
+                    color: Color::BLACK,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform,
+            ..default()
+        },
+        BarkBubble { owner, fade: None },
+        LevelUnload,
+    ));
+}
+
+// the previous bubble may still be fading out when there's a new one to show
+fn despawn_bubbles(commands: &mut Commands, bubbles: &Query<(Entity, &BarkBubble)>, owner: Entity) {
+    for (entity, bubble) in bubbles {
+        if bubble.owner == owner {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn spawn_barks(
+    mut commands: Commands,
+    mut ev_next_to_obj: EventReader<NextToObjEvent>,
+    players: Query<(), With<Player>>,
+    npcs: Query<(&Name, &Transform, &Sprite), With<NPC>>,
+    bubbles: Query<(Entity, &BarkBubble)>,
+    dialogs: Res<DialogLibrary>,
+    settings: Res<BarkSettings>,
+    play_time: Res<PlayTime>,
+    mut cooldowns: ResMut<BarkCooldowns>,
+    font: Res<DialogFont>,
+    fonts: Res<Assets<Font>>,
+) {
+    // NPCs bark at the player, not at each other
+    for ev in ev_next_to_obj
+        .iter()
+        .filter(|ev| players.contains(ev.observer))
+    {
+        let (name, transform, sprite) = match npcs.get(ev.entity) {
+            Ok(npc) => npc,
+            Err(_) => continue,
+        };
+        let graph = match dialogs.get(&name.value) {
+            Some(graph) => graph,
+            None => continue,
+        };
+        let cooldown = match graph.bark_cooldown {
+            // too long to fit is never again, the validator tells about it
+            Some(seconds) => Duration::try_from_secs_f32(seconds.max(0.)).unwrap_or(Duration::MAX),
+            None => settings.cooldown,
+        };
+        let line = match cooldowns.pick(&name.value, &graph.barks, play_time.value, cooldown, |n| {
+            fastrand::usize(..n)
+        }) {
+            Some(line) => line,
+            None => continue,
+        };
+
+        despawn_bubbles(&mut commands, &bubbles, ev.entity);
+        spawn_bark_bubble(
+            &mut commands,
+            ev.entity,
+            transform,
+            Some(sprite),
+            line,
+            &font,
+            &fonts,
+        );
+        debug!("{} barks: {}", name.value, line);
+    }
+}
+
+fn fade_out_barks(
+    mut ev_away_from_obj: EventReader<AwayFromObjEvent>,
+    players: Query<(), With<Player>>,
+    settings: Res<BarkSettings>,
+    mut bubbles: Query<&mut BarkBubble>,
+) {
+    for ev in ev_away_from_obj
+        .iter()
+        .filter(|ev| players.contains(ev.observer))
+    {
+        for mut bubble in &mut bubbles {
+            if bubble.owner == ev.entity && bubble.fade.is_none() {
+                bubble.fade = Some(Fade::new(settings.fade_out));
+            }
+        }
+    }
+}
+
+fn fade_barks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bubbles: Query<(
+        Entity,
+        &mut BarkBubble,
+        Option<&mut Sprite>,
+        Option<&mut Text>,
+    )>,
+) {
+    for (entity, mut bubble, sprite, text) in &mut bubbles {
+        let fade = match bubble.fade.as_mut() {
+            Some(fade) => fade,
+            None => continue,
+        };
+        fade.tick(time.delta());
+        if fade.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let alpha = fade.alpha();
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_a(alpha);
+        }
+        if let Some(mut text) = text {
+            for section in &mut text.sections {
+                section.style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+// what pressing the interact key would do, over the current target
+#[derive(Component)]
+struct InteractionPrompt;
+
+const PROMPT_FONT_SIZE: f32 = 18.;
+// including the margin to the object below
+const PROMPT_HEIGHT: f32 = PROMPT_FONT_SIZE + BARK_MARGIN;
+
+fn spawn_interaction_prompt(mut commands: Commands, font: Res<DialogFont>) {
+    let mut transform: Transform = Stacking::InGame.into();
+    transform.translation.z += 0.5;
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.handle.clone(),
+                    font_size: PROMPT_FONT_SIZE,
+                    color: Color::WHITE,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform,
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        InteractionPrompt,
+        LevelUnload,
+    ));
+}
+
+fn prompt_text(key: &str, kind: InteractionKind, name: Option<&Name>) -> String {
+    match name {
+        Some(name) => format!("[{}] {} {}", key, kind.verb(), name.value),
+        None => format!("[{}] {}", key, kind.verb()),
+    }
+}
+
+fn update_interaction_prompt(
+    interaction_target: Res<InteractionTarget>,
+    input_map: Res<InputMap>,
+    targets: Query<
+        (&Interactable, &Transform, Option<&Name>, Option<&Sprite>),
+        Without<InteractionPrompt>,
+    >,
+    mut prompts: Query<(&mut Text, &mut Transform, &mut Visibility), With<InteractionPrompt>>,
+) {
+    let target = interaction_target
+        .get()
+        .and_then(|&entity| targets.get(entity).ok());
+
+    for (mut text, mut transform, mut visibility) in &mut prompts {
+        let (interactable, target_transform, name, sprite) = match target {
+            Some(target) => target,
+            None => {
+                visibility.is_visible = false;
+                continue;
+            }
+        };
+
+        let line = prompt_text(&input_map.label(Action::Interact), interactable.kind, name);
+        // changing the text lays it out again
+        if text.sections[0].value != line {
+            text.sections[0].value = line;
+        }
+        let target_height = sprite
+            .and_then(|sprite| sprite.custom_size)
+            .map_or(0., |size| size.y);
+        transform.translation.x = target_transform.translation.x;
+        transform.translation.y = target_transform.translation.y
+            + target_height / 2.
+            + BARK_MARGIN
+            + PROMPT_FONT_SIZE / 2.;
+        visibility.is_visible = true;
+    }
+}
+
+// gamepads can be plugged in and out while playing
+fn read_gamepads(mut events: EventReader<GamepadEvent>, mut controllers: ResMut<Controllers>) {
+    controllers.clear();
+    for ev in events.iter() {
+        match &ev.event_type {
+            GamepadEventType::Connected(info) => {
+                info!("{:?} connected: {}", ev.gamepad, info.name)
+            }
+            GamepadEventType::Disconnected => info!("{:?} disconnected", ev.gamepad),
+            _ => (),
+        }
+        controllers.handle(ev);
+    }
+}
+
+fn read_actions(
+    input_map: Res<InputMap>,
+    mut actions: ResMut<Input<Action>>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    controllers: Res<Controllers>,
+) {
+    update_actions(
+        &input_map,
+        &mut actions,
+        &keys,
+        &mouse,
+        controllers.buttons(),
+    );
+}
+
+fn interact_trigger(
+    app_state: Res<State<AppState>>,
+    interaction_target: Res<InteractionTarget>,
+    interactables: Query<&Interactable>,
+    mut flags: ResMut<GameFlags>,
+    mut ev_interact: EventWriter<InteractEvent>,
+) {
+    if app_state.current() != &AppState::InGame {
+        return;
+    }
+    let entity = match interaction_target.get() {
+        Some(&entity) => entity,
+        None => return,
+    };
+    if let Ok(interactable) = interactables.get(entity) {
+        interact(entity, interactable, &mut flags, &mut ev_interact);
+    }
+}
+
+fn interact(
+    entity: Entity,
+    interactable: &Interactable,
+    flags: &mut GameFlags,
+    ev_interact: &mut EventWriter<InteractEvent>,
+) {
+    for effect in &interactable.effects {
+        if let Err(e) = effect.apply(flags) {
+            warn!("interaction effect `{}`: {}", effect.source(), e);
+        }
+    }
+    ev_interact.send(InteractEvent {
+        entity,
+        kind: interactable.kind,
+    });
+}
+
+fn input_interact_trigger(
+    actions: Res<Input<Action>>,
+    app_state: Res<State<AppState>>,
+    interaction_target: Res<InteractionTarget>,
+    interactables: Query<&Interactable>,
+    flags: ResMut<GameFlags>,
+    ev_interact: EventWriter<InteractEvent>,
+) {
+    if actions.just_pressed(Action::Interact) {
+        interact_trigger(
+            app_state,
+            interaction_target,
+            interactables,
+            flags,
+            ev_interact,
+        );
+    }
+}
+
+fn talk_handler(
+    mut ev_interact: EventReader<InteractEvent>,
+    npcs: Query<&Name, With<NPC>>,
+    dialogs: Res<DialogLibrary>,
+    mut active_dialog: ResMut<ActiveDialog>,
+    mut flags: ResMut<GameFlags>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for ev in ev_interact.iter() {
+        if ev.kind != InteractionKind::Talk {
+            continue;
+        }
+        let name = match npcs.get(ev.entity) {
+            Ok(name) => name,
+            Err(_) => {
+                warn!("{:?} has nobody to talk to", ev.entity);
+                continue;
+            }
+        };
+
+        let graph = dialogs
+            .get(&name.value)
+            .cloned()
+            .unwrap_or_else(|| DialogGraph::fallback(&name.value));
+        // talking to whoever was walked up to can come along with pausing and the like
+        if let Err(e) = app_state.push(AppState::DialogWindow) {
+            warn!("not talking to {}: {}", name.value, e);
+            break;
+        }
+        active_dialog.value = Some(Conversation::new(ev.entity, graph, &mut flags));
+        // a single conversation at a time
+        break;
+    }
+}
+
+fn examine_handler(
+    mut commands: Commands,
+    mut ev_interact: EventReader<InteractEvent>,
+    objects: Query<(&Transform, Option<&Sprite>, &Description)>,
+    bubbles: Query<(Entity, &BarkBubble)>,
+    font: Res<DialogFont>,
+    fonts: Res<Assets<Font>>,
+) {
+    for ev in ev_interact.iter() {
+        if ev.kind != InteractionKind::Examine {
+            continue;
+        }
+        let (transform, sprite, description) = match objects.get(ev.entity) {
+            Ok(object) => object,
+            Err(_) => {
+                warn!("{:?} has nothing to examine", ev.entity);
+                continue;
+            }
+        };
+
+        // fades away like barks do once the player walks away
+        despawn_bubbles(&mut commands, &bubbles, ev.entity);
+        spawn_bark_bubble(
+            &mut commands,
+            ev.entity,
+            transform,
+            sprite,
+            &description.value,
+            &font,
+            &fonts,
+        );
+    }
+}
+
+fn pick_up_handler(
+    mut commands: Commands,
+    mut ev_interact: EventReader<InteractEvent>,
+    names: Query<&Name>,
+    mut inventory: ResMut<Inventory>,
+) {
+    for ev in ev_interact.iter() {
+        if ev.kind != InteractionKind::PickUp {
+            continue;
+        }
+        if let Ok(name) = names.get(ev.entity) {
+            inventory.items.push(name.value.clone());
+            debug!("Picked up {}", name.value);
+        }
+        commands.entity(ev.entity).despawn();
+    }
+}
+
+fn open_handler(
+    mut commands: Commands,
+    mut ev_interact: EventReader<InteractEvent>,
+    mut objects: Query<(&mut Openable, Option<&mut Sprite>)>,
+) {
+    for ev in ev_interact.iter() {
+        if ev.kind != InteractionKind::Open {
+            continue;
+        }
+        let (mut openable, sprite) = match objects.get_mut(ev.entity) {
+            Ok(object) => object,
+            Err(_) => {
+                warn!("{:?} has nothing to open", ev.entity);
+                continue;
+            }
+        };
+
+        openable.open = !openable.open;
+        // see-through and passable while open
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_a(if openable.open { 0.3 } else { 1. });
+            if openable.open {
+                commands.entity(ev.entity).remove::<Collider>();
+            } else {
+                commands
+                    .entity(ev.entity)
+                    .insert(Collider::from_sprite(&sprite));
+            }
+        }
+    }
+}
+
+// using is all about effects, which apply to every kind of interaction
+fn use_handler(mut ev_interact: EventReader<InteractEvent>, names: Query<&Name>) {
+    for ev in ev_interact.iter() {
+        if ev.kind != InteractionKind::Use {
+            continue;
+        }
+        if let Ok(name) = names.get(ev.entity) {
+            debug!("Used {}", name.value);
+        }
+    }
+}
+
+fn player_movement(
+    timestep: Res<Timestep>,
+    actions: Res<Input<Action>>,
+    controllers: Res<Controllers>,
+    stick_settings: Res<StickSettings>,
+    settings: Res<MovementSettings>,
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut Facing,
+            &KinematicBody,
+            Option<&mut Route>,
+        ),
+        With<Player>,
+    >,
+    colliders: Query<(&Transform, &Collider), Without<Player>>,
+) {
+    let (player, mut transform, mut velocity, mut facing, body, route) =
+        match query.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+    let position = transform.translation.truncate();
+
+    // the stick goes over the keys, tilted part way is walking slower
+    let stick = stick_vector(controllers.stick(), stick_settings.deadzone);
+    let steered = if stick != Vec2::ZERO {
+        stick
+    } else {
+        let axis = |positive, negative| {
+            actions.pressed(positive) as i8 as f32 - actions.pressed(negative) as i8 as f32
+        };
+        Vec2::new(
+            axis(Action::MoveRight, Action::MoveLeft),
+            axis(Action::MoveUp, Action::MoveDown),
+        )
+    };
+
+    // walking after a click until steered any other way
+    let mut following = false;
+    let input = match route {
+        Some(_) if steered != Vec2::ZERO => {
+            commands.entity(player).remove::<Route>();
+            steered
+        }
+        Some(mut route) => {
+            let input = follow(&mut route.waypoints, position, NAV_CELL_SIZE, &settings);
+            following = input != Vec2::ZERO;
+            if route.waypoints.is_empty() {
+                match route.interact_with.and_then(|npc| colliders.get(npc).ok()) {
+                    // the last tick, route_interaction had a chance to see them acquired
+                    Some(_) if route.patience.is_zero() => {
+                        debug!("gave up on interacting with {:?}", route.interact_with);
+                        commands.entity(player).remove::<Route>();
+                    }
+                    // to be in their sight as well
+                    Some((npc_transform, _)) => {
+                        let to_npc = npc_transform.translation.truncate() - position;
+                        facing.value = to_npc.try_normalize().unwrap_or(facing.value);
+                        route.patience = route.patience.saturating_sub(timestep.step());
+                    }
+                    None => {
+                        commands.entity(player).remove::<Route>();
+                    }
+                }
+            }
+            input
+        }
+        None => steered,
+    };
+    if input != Vec2::ZERO {
+        facing.value = input.normalize();
+    }
+
+    // if left {
+    //     transform.rotation = Quat::from_rotation_y(PI);
+    // } else if right {
+    //     transform.rotation = Quat::default();
+    // }
+
+    let delta = timestep.step().as_secs_f32();
+    let sprinting = actions.pressed(Action::Sprint);
+    velocity.value = steer(velocity.value, input, sprinting, &settings, delta);
+
+    slide_player(&mut transform, body, velocity.value * delta, &colliders);
+    let moved = transform.translation.truncate() - position;
+    // walking into a wall takes the speed off, instead of it building up against it
+    velocity.value = moved / delta;
+
+    // something's in the way that wasn't there when the route was found, like a closed door
+    if following && moved.length() < STUCK {
+        debug!("route blocked at {}", position);
+        commands.entity(player).remove::<Route>();
+    }
+}
+
+const NAV_CELL_SIZE: f32 = 10.;
+// how far the grid goes past the outermost things in the level
+const NAV_MARGIN: f32 = 100.;
+// on top of the dwell, a few ticks for the target to be acquired after arriving
+const ROUTE_PATIENCE: Duration = Duration::from_millis(100);
+// moving less than that in a tick, following a route gave up on it
+const STUCK: f32 = 0.01;
+
+// where the player can walk, kept up to date with the level
+#[derive(Resource)]
+struct NavigationGrid {
+    value: NavGrid,
+}
+
+// nowhere to walk until there's a level
+impl Default for NavigationGrid {
+    fn default() -> Self {
+        Self {
+            value: NavGrid::new(Vec2::ZERO, Vec2::ZERO, NAV_CELL_SIZE),
+        }
+    }
+}
+
+// the player clicked there, to walk there
+struct MoveToEvent {
+    position: Vec2,
+}
+
+// Keeps up with the level in any state, every frame as removals don't last until a tick,
+// and after Update as removals by its commands are only applied at its end.
+// The grid covers colliders, whatever can be walked up to and the player, with a margin.
+fn update_navigation_grid(
+    changed: Query<
+        (),
+        (
+            Or<(With<Collider>, With<InProximity>)>,
+            Or<(Changed<Collider>, Changed<Transform>)>,
+        ),
+    >,
+    removed: RemovedComponents<Collider>,
+    changed_body: Query<(), (With<Player>, Changed<KinematicBody>)>,
+    colliders: Query<(&Transform, &Collider)>,
+    landmarks: Query<&Transform, With<InProximity>>,
+    player: Query<(&Transform, &KinematicBody), With<Player>>,
+    mut grid: ResMut<NavigationGrid>,
+) {
+    // the player is the one that walks it
+    let (player_transform, body) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let player_at = player_transform.translation.truncate();
+    let off_grid = grid.value.cell(player_at).is_none();
+    if changed.is_empty() && changed_body.is_empty() && removed.iter().next().is_none() && !off_grid
+    {
+        return;
+    }
+
+    let (min, max) = colliders
+        .iter()
+        .map(|(transform, collider)| (transform, collider.shape.bounding_radius()))
+        .chain(landmarks.iter().map(|transform| (transform, 0.)))
+        .map(|(transform, radius)| (transform.translation.truncate(), radius))
+        .chain([(player_at, body.shape.bounding_radius())])
+        .fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), (at, radius)| (min.min(at - radius), max.max(at + radius)),
+        );
+    let margin = Vec2::splat(NAV_MARGIN);
+    grid.value = NavGrid::new(min - margin, max + margin, NAV_CELL_SIZE);
+    for (transform, collider) in &colliders {
+        grid.value.block(
+            &body.shape,
+            &collider.shape,
+            transform.translation.truncate(),
+        );
+    }
+}
+
+fn input_move_to_trigger(
+    actions: Res<Input<Action>>,
+    windows: Res<Windows>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut ev_move_to: EventWriter<MoveToEvent>,
+) {
+    if !actions.just_pressed(Action::MoveTo) {
+        return;
+    }
+    let cursor = match windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+    {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    let world = cameras
+        .iter()
+        .find_map(|(camera, camera_transform)| camera.viewport_to_world(camera_transform, cursor));
+    if let Some(ray) = world {
+        ev_move_to.send(MoveToEvent {
+            position: ray.origin.truncate(),
+        });
+    }
+}
+
+// Finds the way to where the player clicked. Clicking an NPC is walking up
+// to them, near enough to talk.
+fn move_to_handler(
+    mut commands: Commands,
+    mut ev_move_to: EventReader<MoveToEvent>,
+    grid: Res<NavigationGrid>,
+    player: Query<(Entity, &Transform, Option<&ProximityShape>), With<Player>>,
+    npcs: Query<
+        (
+            Entity,
+            &Transform,
+            &Collider,
+            &InProximity,
+            Option<&ProximityShape>,
+        ),
+        With<NPC>,
+    >,
+    occluders: Query<(Entity, &Transform, &Occluder)>,
+) {
+    // only the last click counts
+    let position = match ev_move_to.iter().last() {
+        Some(ev) => ev.position,
+        None => return,
+    };
+    let (player, player_transform, player_shape) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let from = player_transform.translation.truncate();
+
+    let clicked_npc = npcs.iter().find(|(_, transform, collider, ..)| {
+        gap(
+            &Shape::Point,
+            position,
+            &collider.shape,
+            transform.translation.truncate(),
+        ) <= 0.
+    });
+    let (path, interact_with, patience) = match clicked_npc {
+        Some((npc, transform, _, in_proximity, npc_shape)) => {
+            let npc_at = transform.translation.truncate();
+            // measured like next_to_obj_watcher does, and well within, not to stop on the edge
+            let player_shape = player_shape.map_or(&Shape::Point, |shape| &shape.value);
+            let npc_shape = npc_shape.map_or(&Shape::Point, |shape| &shape.value);
+            let within = in_proximity.range.enter - grid.value.cell_size();
+            // and with nothing in between, or they'd never become the target
+            let path = grid.value.find_path(from, npc_at, |at| {
+                gap(player_shape, at, npc_shape, npc_at) < within
+                    && in_line_of_sight(at, npc_at, npc, &occluders)
+            });
+            let patience = in_proximity.range.dwell + ROUTE_PATIENCE;
+            (path, Some(npc), patience)
+        }
+        None => (grid.value.path_to(from, position), None, Duration::ZERO),
+    };
+
+    match path {
+        Some(path) => {
+            commands.entity(player).insert(Route {
+                waypoints: path.into(),
+                interact_with,
+                patience,
+            });
+        }
+        None => {
+            debug!("no way to {}", position);
+            commands.entity(player).remove::<Route>();
+        }
+    }
+}
+
+// talks to whoever was clicked once they're in range and in sight
+fn route_interaction(
+    mut commands: Commands,
+    app_state: Res<State<AppState>>,
+    player: Query<(Entity, &Route), With<Player>>,
+    interaction_target: Res<InteractionTarget>,
+    interactables: Query<&Interactable>,
+    mut flags: ResMut<GameFlags>,
+    mut ev_interact: EventWriter<InteractEvent>,
+) {
+    if app_state.current() != &AppState::InGame {
+        return;
+    }
+    let (player, route) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let entity = match route.interact_with {
+        Some(entity) if route.waypoints.is_empty() => entity,
+        _ => return,
+    };
+    let interactable = match interactables.get(entity) {
+        Ok(interactable) => interactable,
+        // gone by now
+        Err(_) => {
+            commands.entity(player).remove::<Route>();
+            return;
+        }
+    };
+    if !interaction_target.value.contains(&entity) {
+        return;
+    }
+
+    commands.entity(player).remove::<Route>();
+    interact(entity, interactable, &mut flags, &mut ev_interact);
+}
+
+fn slide_player(
+    transform: &mut Transform,
+    body: &KinematicBody,
+    motion: Vec2,
+    colliders: &Query<(&Transform, &Collider), Without<Player>>,
+) {
+    if motion == Vec2::ZERO {
+        return;
+    }
+    let colliders: Vec<(&Shape, Vec2)> = colliders
+        .iter()
+        .map(|(transform, collider)| (&collider.shape, transform.translation.truncate()))
+        .collect();
+    let position = move_and_slide(
+        &body.shape,
+        transform.translation.truncate(),
+        motion,
+        &colliders,
+    );
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+}
+
+#[derive(Component)]
+struct PauseScreen;
+
+#[derive(Bundle)]
+struct PauseScreenBundle {
+    sprite: SpriteBundle,
+    _ps: PauseScreen,
+}
+
+fn setup_pause_screen(mut commands: Commands) {
+    commands.spawn(PauseScreenBundle {
+        sprite: SpriteBundle {
+            sprite: Sprite {
+                color: Color::SEA_GREEN,
+                custom_size: Some(Vec2::new(100., 100.)),
+                ..default()
+            },
+            transform: Stacking::PauseScreen.into(),
+            ..default()
+        },
+        _ps: PauseScreen,
+    });
+}
 
 #[derive(Component)]
 struct DialogWindow;
 
-#[derive(Bundle)]
-struct DialogWindowBundle {
-    sprite: SpriteBundle,
-    _dw: DialogWindow,
+#[derive(Bundle)]
+struct DialogWindowBundle {
+    sprite: SpriteBundle,
+    _dw: DialogWindow,
+}
+
+// text of the current dialog node
+#[derive(Component)]
+struct DialogText;
+
+// replies to the current dialog node
+#[derive(Component)]
+struct DialogChoices;
+
+// tells there are more pages to the current line
+#[derive(Component)]
+struct DialogMore;
+
+// picture of whoever speaks the current line
+#[derive(Component, Default)]
+struct DialogPortrait {
+    image: Option<String>,
+}
+
+// what part of the dialog box a text is attached to
+#[derive(Component, Clone, Copy)]
+enum DialogAnchor {
+    Text,
+    Choices,
+    More,
+}
+
+// Dialog box placement, in world units which are logical pixels with the default camera
+#[derive(Resource)]
+struct DialogLayout {
+    size: Vec2,
+    center: Vec2,
+    padding: f32,
+    // reserved at the left of the box in conversations with portraits
+    portrait_size: Vec2,
+    speaker_font_size: f32,
+    line_font_size: f32,
+    choice_font_size: f32,
+}
+
+impl Default for DialogLayout {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(800., 200.),
+            center: Vec2::new(0., -200.),
+            padding: 20.,
+            portrait_size: Vec2::new(100., 160.),
+            speaker_font_size: 26.,
+            line_font_size: 32.,
+            choice_font_size: 24.,
+        }
+    }
+}
+
+impl DialogLayout {
+    // the box corner in UI coordinates, which start at the top left corner of the window
+    fn top_left(&self, resolution: &ScreenResolution) -> Vec2 {
+        Vec2::new(
+            f32::from(resolution.width()) / 2. + self.center.x - self.size.x / 2.,
+            f32::from(resolution.height()) / 2. - self.center.y - self.size.y / 2.,
+        )
+    }
+
+    // where text starts relative to the left side of the box
+    fn text_offset(&self, portrait: bool) -> f32 {
+        if portrait {
+            self.portrait_size.x + 2. * self.padding
+        } else {
+            self.padding
+        }
+    }
+
+    fn text_width(&self, portrait: bool) -> f32 {
+        self.size.x - self.text_offset(portrait) - self.padding
+    }
+
+    fn portrait_transform(&self) -> Transform {
+        let mut transform = Stacking::DialogWindow.from_xy(
+            self.center.x - self.size.x / 2. + self.padding + self.portrait_size.x / 2.,
+            self.center.y,
+        );
+        // in front of the box
+        transform.translation.z += 0.5;
+        transform
+    }
+
+    fn position(
+        &self,
+        anchor: DialogAnchor,
+        resolution: &ScreenResolution,
+        portrait: bool,
+    ) -> UiRect {
+        let corner = self.top_left(resolution);
+        let bottom = f32::from(resolution.height()) - corner.y - self.size.y;
+
+        match anchor {
+            DialogAnchor::Text => UiRect {
+                left: Px(corner.x + self.text_offset(portrait)),
+                top: Px(corner.y + self.padding),
+                ..default()
+            },
+            // replies stack up right above the box
+            DialogAnchor::Choices => UiRect {
+                left: Px(corner.x + self.padding),
+                bottom: Px(bottom + self.size.y + self.padding / 2.),
+                ..default()
+            },
+            DialogAnchor::More => UiRect {
+                right: Px(f32::from(resolution.width()) - corner.x - self.size.x + self.padding),
+                bottom: Px(bottom + self.padding / 2.),
+                ..default()
+            },
+        }
+    }
+
+    fn style(
+        &self,
+        anchor: DialogAnchor,
+        resolution: &CurrentScreenResolution,
+        portrait: bool,
+    ) -> Style {
+        Style {
+            position_type: PositionType::Absolute,
+            position: match &resolution.value {
+                Some(resolution) => self.position(anchor, resolution, portrait),
+                None => UiRect::default(),
+            },
+            ..default()
+        }
+    }
+
+    // splits text into pages fitting under the speaker name
+    fn pages(&self, text: &str, font: Option<ab_glyph::FontArc>, portrait: bool) -> Vec<String> {
+        let speaker = TextMeasure::new(font.clone(), self.speaker_font_size);
+        let line = TextMeasure::new(font, self.line_font_size);
+
+        let height = self.size.y - 2. * self.padding - speaker.line_height();
+        let lines_per_page = (height / line.line_height()).floor() as usize;
+        let lines = wrap(text, self.text_width(portrait), |text| line.width(text));
+        paginate(&lines, lines_per_page)
+    }
+}
+
+#[derive(Resource)]
+struct DialogFont {
+    handle: Handle<Font>,
+}
+
+struct Conversation {
+    npc: Entity,
+    graph: DialogGraph,
+    cursor: DialogCursor,
+    // index of the highlighted choice
+    selected: usize,
+    // current line split to fit the box, empty until laid out
+    pages: Vec<String>,
+    page: usize,
+    // how much of the current page is shown
+    reveal: Typewriter,
+}
+
+impl Conversation {
+    fn new(npc: Entity, graph: DialogGraph, flags: &mut GameFlags) -> Self {
+        Self {
+            npc,
+            cursor: DialogCursor::start(&graph, flags),
+            graph,
+            selected: 0,
+            pages: vec![],
+            page: 0,
+            reveal: Typewriter::new(""),
+        }
+    }
+
+    fn current(&self) -> Option<&DialogNode> {
+        self.cursor.current(&self.graph)
+    }
+
+    // reserves room for portraits for the whole conversation, so text doesn't jump around
+    fn has_portraits(&self) -> bool {
+        !self.graph.portraits.is_empty()
+    }
+
+    fn portrait_image(&self) -> Option<&str> {
+        self.current()
+            .and_then(|node| self.graph.portrait_image(node))
+    }
+
+    fn needs_layout(&self) -> bool {
+        self.pages.is_empty() && !self.cursor.is_finished()
+    }
+
+    fn lay_out(&mut self, layout: &DialogLayout, font: Option<ab_glyph::FontArc>) {
+        let text = self.current().map_or("", |node| node.text.as_str());
+        self.pages = layout.pages(text, font, self.has_portraits());
+        self.show_page(0);
+    }
+
+    fn show_page(&mut self, page: usize) {
+        self.page = page;
+        self.reveal = Typewriter::new(&self.pages[page]);
+    }
+
+    fn has_more_pages(&self) -> bool {
+        self.page + 1 < self.pages.len()
+    }
+
+    // all pages of the current line are shown, it's time to reply
+    fn is_line_finished(&self) -> bool {
+        !self.needs_layout() && !self.has_more_pages() && self.reveal.is_finished()
+    }
+
+    // only the choices available with the current story progress
+    fn choices(&self, flags: &GameFlags) -> Vec<(usize, &DialogChoice)> {
+        self.cursor.choices(&self.graph, flags)
+    }
+
+    fn select_next(&mut self, flags: &GameFlags) {
+        let len = self.choices(flags).len();
+        if len > 0 && self.is_line_finished() {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    fn select_previous(&mut self, flags: &GameFlags) {
+        let len = self.choices(flags).len();
+        if len > 0 && self.is_line_finished() {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    // finishes the page being revealed first, then turns the page, then
+    // picks the highlighted choice, or moves on when there's nothing to choose from
+    fn confirm(&mut self, flags: &mut GameFlags) {
+        if self.needs_layout() {
+            return;
+        }
+        if !self.reveal.is_finished() {
+            self.reveal.finish();
+            return;
+        }
+        if self.has_more_pages() {
+            self.show_page(self.page + 1);
+            return;
+        }
+
+        match self.choices(flags).get(self.selected) {
+            Some(&(idx, _)) => self.cursor.choose(&self.graph, idx, flags),
+            None => self.cursor.advance(&self.graph, flags),
+        }
+        self.selected = 0;
+        self.pages.clear();
+    }
+}
+
+#[derive(Resource, Default)]
+struct ActiveDialog {
+    value: Option<Conversation>,
+}
+
+fn load_dialogs(mut commands: Commands) {
+    let dir = DialogLibrary::default_dir();
+    let library =
+        DialogLibrary::load_dir(&dir).unwrap_or_else(|e| panic!("failed to load dialogs: {}", e));
+    debug!(
+        "loaded {}x dialogs from {}",
+        library.iter().count(),
+        dir.display()
+    );
+    commands.insert_resource(library);
+}
+
+fn load_movement_settings(mut commands: Commands) {
+    let path = MovementSettings::default_path();
+    let settings = MovementSettings::load(&path)
+        .unwrap_or_else(|e| panic!("failed to load movement settings: {}", e));
+    debug!("{:?}", settings);
+    commands.insert_resource(settings);
+}
+
+fn load_game_flags(mut commands: Commands) {
+    let path = GameFlags::default_path();
+    let flags =
+        GameFlags::load(&path).unwrap_or_else(|e| panic!("failed to load game flags: {}", e));
+    debug!("declared {}x game flags", flags.iter().count());
+    commands.insert_resource(flags);
+}
+
+// kept loaded, so that dialog text can be measured as soon as a dialog opens
+fn load_dialog_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DialogFont {
+        handle: asset_server.load("fonts/OpenSans.ttf"),
+    });
+}
+
+fn dialog_text_sections(
+    conversation: &Conversation,
+    layout: &DialogLayout,
+    font: &DialogFont,
+) -> Vec<TextSection> {
+    match conversation.current() {
+        Some(node) => vec![
+            TextSection::new(
+                format!("{}\n", conversation.graph.speaker(node)),
+                TextStyle {
+                    font: font.handle.clone(),
+                    font_size: layout.speaker_font_size,
+                    color: Color::GOLD,
+                },
+            ),
+            TextSection::new(
+                conversation.reveal.visible(),
+                TextStyle {
+                    font: font.handle.clone(),
+                    font_size: layout.line_font_size,
+                    color: Color::WHITE,
+                },
+            ),
+        ],
+        None => vec![],
+    }
+}
+
+fn dialog_choice_sections(
+    conversation: &Conversation,
+    flags: &GameFlags,
+    layout: &DialogLayout,
+    font: &DialogFont,
+) -> Vec<TextSection> {
+    if !conversation.is_line_finished() {
+        return vec![];
+    }
+
+    conversation
+        .choices(flags)
+        .iter()
+        .enumerate()
+        .map(|(idx, (_, choice))| {
+            let (marker, color) = if idx == conversation.selected {
+                ("> ", Color::YELLOW)
+            } else {
+                ("  ", Color::WHITE)
+            };
+            TextSection::new(
+                format!("{}{}\n", marker, choice.text),
+                TextStyle {
+                    font: font.handle.clone(),
+                    font_size: layout.choice_font_size,
+                    color,
+                },
+            )
+        })
+        .collect()
+}
+
+// the conversation is started by whoever pushes the state
+fn setup_dialog_window(
+    mut commands: Commands,
+    active_dialog: Res<ActiveDialog>,
+    layout: Res<DialogLayout>,
+    resolution: Res<CurrentScreenResolution>,
+    font: Res<DialogFont>,
+) {
+    let portrait = active_dialog
+        .value
+        .as_ref()
+        .is_some_and(Conversation::has_portraits);
+
+    commands.spawn(DialogWindowBundle {
+        sprite: SpriteBundle {
+            sprite: Sprite {
+                color: Color::OLIVE,
+                custom_size: Some(layout.size),
+                ..default()
+            },
+            transform: Stacking::DialogWindow.from_xy(layout.center.x, layout.center.y),
+            ..default()
+        },
+        _dw: DialogWindow,
+    });
+
+    // text is filled in once the line is laid out
+    commands.spawn((
+        TextBundle::default()
+            .with_text_alignment(TextAlignment::TOP_LEFT)
+            .with_style(layout.style(DialogAnchor::Text, &resolution, portrait)),
+        DialogAnchor::Text,
+        DialogText,
+        DialogWindow,
+    ));
+
+    commands.spawn((
+        TextBundle::default()
+            .with_text_alignment(TextAlignment::BOTTOM_LEFT)
+            .with_style(layout.style(DialogAnchor::Choices, &resolution, portrait)),
+        DialogAnchor::Choices,
+        DialogChoices,
+        DialogWindow,
+    ));
+
+    let mut more = TextBundle::from_section(
+        "more...",
+        TextStyle {
+            font: font.handle.clone(),
+            font_size: layout.choice_font_size,
+            color: Color::GOLD,
+        },
+    )
+    .with_style(layout.style(DialogAnchor::More, &resolution, portrait));
+    more.visibility.is_visible = false;
+    commands.spawn((more, DialogAnchor::More, DialogMore, DialogWindow));
+
+    // the image is picked once the current line is known
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(layout.portrait_size),
+                ..default()
+            },
+            transform: layout.portrait_transform(),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        DialogPortrait::default(),
+        DialogWindow,
+    ));
+}
+
+// a line gets laid out once, as soon as it's shown, so it's also written down here
+fn lay_out_dialog(
+    layout: Res<DialogLayout>,
+    font: Res<DialogFont>,
+    fonts: Res<Assets<Font>>,
+    play_time: Res<PlayTime>,
+    mut transcript: ResMut<Transcript>,
+    mut active_dialog: ResMut<ActiveDialog>,
+) {
+    let conversation = match active_dialog.bypass_change_detection().value.as_mut() {
+        Some(conversation) if conversation.needs_layout() => conversation,
+        _ => return,
+    };
+    let font = fonts.get(&font.handle).map(|font| font.font.clone());
+
+    conversation.lay_out(&layout, font);
+    if let Some(node) = conversation.current() {
+        transcript.push(TranscriptEntry {
+            npc: conversation.graph.npc.clone(),
+            speaker: conversation.graph.speaker(node).into(),
+            text: node.text.clone(),
+            at: play_time.value,
+        });
+    }
+    active_dialog.set_changed();
+}
+
+// follows screen resolution changes
+fn place_dialog_window(
+    layout: Res<DialogLayout>,
+    resolution: Res<CurrentScreenResolution>,
+    active_dialog: Res<ActiveDialog>,
+    mut anchors: Query<(&DialogAnchor, &mut Style)>,
+) {
+    if !(layout.is_changed() || resolution.is_changed()) {
+        return;
+    }
+    let portrait = active_dialog
+        .value
+        .as_ref()
+        .is_some_and(Conversation::has_portraits);
+    for (anchor, mut style) in &mut anchors {
+        *style = layout.style(*anchor, &resolution, portrait);
+    }
+}
+
+// swaps the picture as speakers and their expressions change
+fn update_dialog_portrait(
+    active_dialog: Res<ActiveDialog>,
+    asset_server: Res<AssetServer>,
+    mut portraits: Query<(&mut DialogPortrait, &mut Handle<Image>, &mut Visibility)>,
+) {
+    if !active_dialog.is_changed() {
+        return;
+    }
+    let image = active_dialog
+        .value
+        .as_ref()
+        .and_then(Conversation::portrait_image);
+
+    for (mut portrait, mut texture, mut visibility) in &mut portraits {
+        if portrait.image.as_deref() == image {
+            continue;
+        }
+        if let Some(image) = image {
+            *texture = asset_server.load(image);
+        }
+        visibility.is_visible = image.is_some();
+        portrait.image = image.map(String::from);
+    }
+}
+
+fn dialog_typewriter(
+    time: Res<Time>,
+    settings: Res<TypewriterSettings>,
+    mut active_dialog: ResMut<ActiveDialog>,
+) {
+    // touch the resource only when something new shows up, it triggers a text update
+    let revealed = match active_dialog.bypass_change_detection().value.as_mut() {
+        Some(conversation) => conversation.reveal.tick(time.delta_seconds(), &settings),
+        None => false,
+    };
+    if revealed {
+        active_dialog.set_changed();
+    }
+}
+
+fn update_dialog_text(
+    active_dialog: Res<ActiveDialog>,
+    mut texts: Query<&mut Text, (With<DialogText>, Without<DialogChoices>)>,
+    mut choices: Query<&mut Text, (With<DialogChoices>, Without<DialogText>)>,
+    mut more: Query<&mut Visibility, With<DialogMore>>,
+    flags: Res<GameFlags>,
+    layout: Res<DialogLayout>,
+    font: Res<DialogFont>,
+) {
+    if !(active_dialog.is_changed() || flags.is_changed()) {
+        return;
+    }
+    if let Some(conversation) = &active_dialog.value {
+        for mut text in &mut texts {
+            text.sections = dialog_text_sections(conversation, &layout, &font);
+        }
+        for mut text in &mut choices {
+            text.sections = dialog_choice_sections(conversation, &flags, &layout, &font);
+        }
+        for mut visibility in &mut more {
+            visibility.is_visible =
+                conversation.reveal.is_finished() && conversation.has_more_pages();
+        }
+    }
+}
+
+// time spent playing, the clock of transcript timestamps
+#[derive(Resource, Default)]
+struct PlayTime {
+    value: Duration,
+}
+
+fn tick_play_time(time: Res<Time>, mut play_time: ResMut<PlayTime>) {
+    play_time.value += time.delta();
+}
+
+#[derive(Component)]
+struct History;
+
+#[derive(Component)]
+struct HistoryTitle;
+
+#[derive(Component)]
+struct HistoryLines;
+
+// part of the transcript shown on the history screen
+#[derive(Resource, Default)]
+struct HistoryView {
+    // only lines from conversations with this NPC, all of them if None
+    filter: Option<String>,
+    // lines scrolled up from the latest one
+    scroll: usize,
+    max_scroll: usize,
+}
+
+const HISTORY_VISIBLE_LINES: usize = 14;
+const HISTORY_FONT_SIZE: f32 = 22.;
+const HISTORY_WIDTH: f32 = 900.;
+
+fn history_lines(
+    transcript: &Transcript,
+    filter: Option<&str>,
+    font: Option<ab_glyph::FontArc>,
+) -> Vec<String> {
+    let measure = TextMeasure::new(font, HISTORY_FONT_SIZE);
+
+    transcript
+        .entries(filter)
+        .flat_map(|entry| {
+            let line = format!("[{}] {}: {}", entry.timestamp(), entry.speaker, entry.text);
+            wrap(&line, HISTORY_WIDTH, |text| measure.width(text))
+        })
+        .collect()
 }
 
-fn setup_dialog_window(
-    mut commands: Commands,
-    npcs: Query<(Entity, &Name), With<NPC>>,
-    nearest_npc_in_proximity: Res<NearestNPCinProximity>,
-    asset_server: Res<AssetServer>,
-) {
-    let entity = nearest_npc_in_proximity.get().unwrap().clone();
-    let name = npcs.get_component::<Name>(entity).unwrap();
+fn setup_history(mut commands: Commands, mut view: ResMut<HistoryView>, font: Res<DialogFont>) {
+    *view = HistoryView::default();
 
-    commands.spawn(DialogWindowBundle {
-        sprite: SpriteBundle {
+    commands.spawn((
+        SpriteBundle {
             sprite: Sprite {
-                color: Color::OLIVE,
-                custom_size: Some(Vec2::new(800., 200.)),
+                color: *Color::MIDNIGHT_BLUE.as_rgba().set_a(0.9),
+                custom_size: Some(Vec2::new(1000., 560.)),
                 ..default()
             },
-            transform: Stacking::DialogWindow.from_xy(0., -200.),
+            transform: Stacking::History.into(),
             ..default()
         },
-        _dw: DialogWindow,
-    });
+        History,
+    ));
 
-    let text = format!("I'm {}", name.value);
+    let style = |font_size| TextStyle {
+        font: font.handle.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
 
     commands.spawn((
-        // Create a TextBundle that has a Text with a single section.
-        TextBundle::from_section(
-            // Accepts a `String` or any type that converts into a `String`, such as `&str`
-            text.as_str(),
-            TextStyle {
-                font: asset_server.load("fonts/OpenSans.ttf"),
-                font_size: 100.,
-                color: Color::WHITE,
+        TextBundle::from_section("", style(28.)).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Px(160.),
+                top: Px(95.),
+                ..default()
             },
-        ) // Set the alignment of the Text
-        .with_text_alignment(TextAlignment::TOP_CENTER)
-        // Set the style of the TextBundle itself.
-        .with_style(Style {
+            ..default()
+        }),
+        HistoryTitle,
+        History,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section("", style(HISTORY_FONT_SIZE)).with_style(Style {
             position_type: PositionType::Absolute,
             position: UiRect {
-                bottom: Px(150.),
-                left: Px(300.),
+                left: Px(160.),
+                top: Px(150.),
                 ..default()
             },
             ..default()
         }),
-        DialogWindow,
+        HistoryLines,
+        History,
     ));
 }
 
+fn history_navigation(
+    actions: Res<Input<Action>>,
+    transcript: Res<Transcript>,
+    mut view: ResMut<HistoryView>,
+) {
+    if actions.just_pressed(Action::MenuUp) {
+        view.scroll = (view.scroll + 1).min(view.max_scroll);
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        view.scroll = view.scroll.saturating_sub(1);
+    }
+    if actions.just_pressed(Action::PageUp) {
+        view.scroll = (view.scroll + HISTORY_VISIBLE_LINES).min(view.max_scroll);
+    }
+    if actions.just_pressed(Action::PageDown) {
+        view.scroll = view.scroll.saturating_sub(HISTORY_VISIBLE_LINES);
+    }
+
+    // cycles through everyone, then every NPC talked to
+    let left = actions.just_pressed(Action::MenuLeft);
+    let right = actions.just_pressed(Action::MenuRight);
+    if left || right {
+        let filters: Vec<Option<&str>> = std::iter::once(None)
+            .chain(transcript.npcs().into_iter().map(Some))
+            .collect();
+        let current = filters
+            .iter()
+            .position(|&filter| filter == view.filter.as_deref())
+            .unwrap_or(0);
+        let next = if right {
+            (current + 1) % filters.len()
+        } else {
+            (current + filters.len() - 1) % filters.len()
+        };
+
+        view.filter = filters[next].map(String::from);
+        view.scroll = 0;
+    }
+}
+
+fn update_history(
+    mut view: ResMut<HistoryView>,
+    transcript: Res<Transcript>,
+    font: Res<DialogFont>,
+    fonts: Res<Assets<Font>>,
+    mut titles: Query<&mut Text, (With<HistoryTitle>, Without<HistoryLines>)>,
+    mut lines: Query<&mut Text, (With<HistoryLines>, Without<HistoryTitle>)>,
+) {
+    if !(view.is_changed() || transcript.is_changed()) {
+        return;
+    }
+    let view = view.bypass_change_detection();
+
+    let font = fonts.get(&font.handle).map(|font| font.font.clone());
+    let all_lines = history_lines(&transcript, view.filter.as_deref(), font);
+    view.max_scroll = all_lines.len().saturating_sub(HISTORY_VISIBLE_LINES);
+    view.scroll = view.scroll.min(view.max_scroll);
+
+    let end = all_lines.len() - view.scroll;
+    let start = end.saturating_sub(HISTORY_VISIBLE_LINES);
+    let shown = if all_lines.is_empty() {
+        "Nobody has said anything yet".to_owned()
+    } else {
+        all_lines[start..end].join("\n")
+    };
+
+    for mut text in &mut titles {
+        text.sections[0].value = format!(
+            "History: {}    (left/right to filter, up/down to scroll)",
+            view.filter.as_deref().unwrap_or("everyone")
+        );
+    }
+    for mut text in &mut lines {
+        text.sections[0].value = shown.clone();
+    }
+}
+
 #[derive(Component)]
 struct MainMenu;
 
@@ -640,6 +2751,7 @@ enum Stacking {
     InGame,
     DialogWindow,
     PauseScreen,
+    History,
     Settings,
     MainMenu,
 }
@@ -651,8 +2763,9 @@ impl Stacking {
             InGame => 0u8,
             DialogWindow => 1,
             PauseScreen => 2,
-            MainMenu => 3,
-            Settings => 4,
+            History => 3,
+            MainMenu => 4,
+            Settings => 5,
         })
         .into()
     }
@@ -689,7 +2802,7 @@ fn main_menu_trigger(mut app_state: ResMut<State<AppState>>) {
         AppState::PauseScreen => app_state.replace(AppState::MainMenu),
         AppState::Settings => app_state.replace(AppState::MainMenu),
         AppState::MainMenu => app_state.replace(AppState::InGame),
-        state @ (AppState::InGame | AppState::DialogWindow) => {
+        state @ (AppState::InGame | AppState::DialogWindow | AppState::History) => {
             warn!("can't go to the main menu from {:?}", state);
             Ok(())
         }
@@ -697,8 +2810,8 @@ fn main_menu_trigger(mut app_state: ResMut<State<AppState>>) {
     .unwrap()
 }
 
-fn keyboard_main_menu_trigger(keys: Res<Input<KeyCode>>, app_state: ResMut<State<AppState>>) {
-    if keys.just_pressed(KeyCode::Tab) {
+fn input_main_menu_trigger(actions: Res<Input<Action>>, app_state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::MainMenu) {
         debug!("current state {:?}", app_state.current());
         main_menu_trigger(app_state);
     }
@@ -708,42 +2821,92 @@ fn pause_screen_trigger(mut app_state: ResMut<State<AppState>>) {
     match app_state.current() {
         AppState::InGame | AppState::DialogWindow => app_state.push(AppState::PauseScreen),
         AppState::PauseScreen => app_state.pop(),
-        AppState::MainMenu | AppState::Settings => Ok(()),
+        AppState::MainMenu | AppState::Settings | AppState::History => Ok(()),
     }
     .unwrap()
 }
 
-fn keyboard_pause_screen_trigger(keys: Res<Input<KeyCode>>, app_state: ResMut<State<AppState>>) {
-    if keys.just_pressed(KeyCode::M) {
+fn input_pause_screen_trigger(actions: Res<Input<Action>>, app_state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Pause) {
         pause_screen_trigger(app_state);
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DialogInput {
+    Confirm,
+    Up,
+    Down,
+    Cancel,
+}
+
+// dialogs get opened by talking to someone, see talk_handler
 fn dialog_window_trigger(
+    input: DialogInput,
     mut app_state: ResMut<State<AppState>>,
-    nearest_npc_in_proximity: Res<NearestNPCinProximity>,
+    mut active_dialog: ResMut<ActiveDialog>,
+    mut flags: ResMut<GameFlags>,
 ) {
     match app_state.current() {
-        AppState::InGame => {
-            if nearest_npc_in_proximity.any() {
-                app_state.push(AppState::DialogWindow)
-            } else {
-                Ok(())
+        AppState::DialogWindow => match active_dialog.value.as_mut() {
+            Some(conversation) => {
+                match input {
+                    DialogInput::Up => conversation.select_previous(&flags),
+                    DialogInput::Down => conversation.select_next(&flags),
+                    DialogInput::Confirm => conversation.confirm(&mut flags),
+                    DialogInput::Cancel => conversation.cursor.finish(),
+                }
+                if conversation.cursor.is_finished() {
+                    app_state.pop()
+                } else {
+                    Ok(())
+                }
             }
-        }
-        AppState::DialogWindow => app_state.pop(),
-        AppState::PauseScreen | AppState::MainMenu | AppState::Settings => Ok(()),
+            None => app_state.pop(),
+        },
+        AppState::InGame
+        | AppState::PauseScreen
+        | AppState::MainMenu
+        | AppState::Settings
+        | AppState::History => Ok(()),
     }
     .unwrap()
 }
 
-fn keyboard_dialog_window_trigger(
-    keys: Res<Input<KeyCode>>,
+fn input_dialog_window_trigger(
+    actions: Res<Input<Action>>,
     app_state: ResMut<State<AppState>>,
-    nearest_npc_in_proximity: Res<NearestNPCinProximity>,
+    active_dialog: ResMut<ActiveDialog>,
+    flags: ResMut<GameFlags>,
 ) {
-    if keys.just_pressed(KeyCode::E) {
-        dialog_window_trigger(app_state, nearest_npc_in_proximity);
+    let input = if actions.just_pressed(Action::Confirm) {
+        DialogInput::Confirm
+    } else if actions.just_pressed(Action::MenuUp) {
+        DialogInput::Up
+    } else if actions.just_pressed(Action::MenuDown) {
+        DialogInput::Down
+    } else if actions.just_pressed(Action::Cancel) {
+        DialogInput::Cancel
+    } else {
+        return;
+    };
+
+    dialog_window_trigger(input, app_state, active_dialog, flags);
+}
+
+fn history_trigger(mut app_state: ResMut<State<AppState>>) {
+    match app_state.current() {
+        // re-reading is for when the game is paused
+        AppState::PauseScreen => app_state.push(AppState::History),
+        AppState::History => app_state.pop(),
+        _ => Ok(()),
+    }
+    .unwrap()
+}
+
+fn input_history_trigger(actions: Res<Input<Action>>, app_state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::History) {
+        history_trigger(app_state);
     }
 }
 
@@ -804,12 +2967,29 @@ fn settings_window_trigger(mut app_state: ResMut<State<AppState>>) {
     .unwrap();
 }
 
-fn keyboard_settings_trigger(keys: Res<Input<KeyCode>>, app_state: ResMut<State<AppState>>) {
-    if keys.just_pressed(KeyCode::R) {
+fn input_settings_trigger(actions: Res<Input<Action>>, app_state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Settings) {
         settings_window_trigger(app_state);
     }
 }
 
+// Out of whatever is on top, dialogs handle backing out of them on their own
+fn back_trigger(mut app_state: ResMut<State<AppState>>) {
+    match app_state.current() {
+        AppState::PauseScreen | AppState::Settings | AppState::History => app_state.pop(),
+        // back to the game, the same as leaving it with MainMenu
+        AppState::MainMenu => app_state.replace(AppState::InGame),
+        AppState::InGame | AppState::DialogWindow => Ok(()),
+    }
+    .unwrap()
+}
+
+fn input_back_trigger(actions: Res<Input<Action>>, app_state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Cancel) {
+        back_trigger(app_state);
+    }
+}
+
 fn init_screen_resolution(
     windows: Res<Windows>,
     mut current_screen_resolution: ResMut<CurrentScreenResolution>,
@@ -822,13 +3002,13 @@ fn init_screen_resolution(
 }
 
 // temporary, for testing
-fn window_scaling(mut windows: ResMut<Windows>, keys: Res<Input<KeyCode>>) {
+fn window_scaling(mut windows: ResMut<Windows>, actions: Res<Input<Action>>) {
     const SCALE: f64 = 0.1;
-    if keys.just_pressed(KeyCode::Equals) {
+    if actions.just_pressed(Action::ScaleUp) {
         let window = windows.get_primary_mut().unwrap();
         window.set_scale_factor_override(Some(window.scale_factor() + SCALE));
     }
-    if keys.just_pressed(KeyCode::Minus) {
+    if actions.just_pressed(Action::ScaleDown) {
         let window = windows.get_primary_mut().unwrap();
         window.set_scale_factor_override(Some(window.scale_factor() - SCALE));
     }
@@ -892,8 +3072,8 @@ impl WindowExt for Window {
     }
 }
 
-fn window_fullscreen(mut windows: ResMut<Windows>, keys: Res<Input<KeyCode>>) {
-    if keys.just_pressed(KeyCode::F) {
+fn window_fullscreen(mut windows: ResMut<Windows>, actions: Res<Input<Action>>) {
+    if actions.just_pressed(Action::Fullscreen) {
         let window = windows.get_primary_mut().unwrap();
         assert!(window.is_valid_mode());
 
@@ -926,6 +3106,30 @@ fn on_window_resize(
 
 mod tests {
     use crate::ScreenResolution;
+    use crate::{
+        away_from_npc_event_handler, in_game, index_proximity_objects, input_back_trigger,
+        input_interact_trigger, input_main_menu_trigger, input_pause_screen_trigger,
+        move_to_handler, next_to_npc_event_handler, next_to_obj_watcher, open_handler,
+        pick_up_handler, player_movement, rank_interactables_in_proximity, read_actions,
+        read_gamepads, route_interaction, talk_handler, update_interaction_prompt,
+        update_navigation_grid, ActiveDialog, AppState, AwayFromObjEvent, Collider, Facing,
+        FacingCone, GameplayStage, GameplayTicksPlugin, InProximity, InteractEvent, Interactable,
+        InteractionKind, InteractionPrompt, InteractionTarget, Interpolated, Inventory,
+        KinematicBody, Label, MoveToEvent, NavigationGrid, NextToObjEvent, Occluder, Openable,
+        Player, ProximityGrid, ProximityObserver, ProximityShape, ProximityToObjResource, Route,
+        TargetChanged, TransformFromXY, Velocity, NPC,
+    };
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::input::gamepad::{GamepadEventType, GamepadInfo};
+    use bevy::prelude::*;
+    use mistery::dialog::DialogLibrary;
+    use mistery::flags::{Effect, FlagValue, GameFlags};
+    use mistery::input::{Action, Binding, Controllers, InputMap, StickSettings};
+    use mistery::movement::MovementSettings;
+    use mistery::proximity::{ProximityRange, ProximityState};
+    use mistery::shape::Shape;
+    use mistery::timestep::Timestep;
+    use std::time::Duration;
 
     #[test]
     fn test_screen_resolution_from_tuple() {
@@ -944,4 +3148,1018 @@ mod tests {
             assert_eq!(resolution, &(result.width(), result.height()));
         }
     }
+
+    // an NPC at the origin and the player walking along the x axis, a frame per position
+    struct ProximityRun {
+        app: App,
+        npc: Entity,
+        player: Entity,
+        next_to: ManualEventReader<NextToObjEvent>,
+        away_from: ManualEventReader<AwayFromObjEvent>,
+    }
+
+    impl ProximityRun {
+        fn new(range: ProximityRange) -> Self {
+            let mut app = App::new();
+            app.insert_resource(Timestep::new(Duration::from_millis(16)))
+                .insert_resource(ProximityToObjResource::default())
+                .insert_resource(ProximityGrid::default())
+                .insert_resource(InteractionTarget::default())
+                .insert_resource(Inventory::default())
+                .add_event::<NextToObjEvent>()
+                .add_event::<AwayFromObjEvent>()
+                .add_event::<TargetChanged>()
+                .add_event::<InteractEvent>()
+                .add_system_to_stage(CoreStage::PostUpdate, index_proximity_objects)
+                .add_system(next_to_obj_watcher.before(rank_interactables_in_proximity))
+                .add_system(rank_interactables_in_proximity)
+                .add_system(pick_up_handler.after(rank_interactables_in_proximity));
+            let npc = app
+                .world
+                .spawn((
+                    Transform::from_xy(0., 0.),
+                    InProximity { range },
+                    Interactable::new(InteractionKind::Talk),
+                ))
+                .id();
+            let player = app
+                .world
+                .spawn((
+                    Transform::from_xy(500., 0.),
+                    Facing::default(),
+                    ProximityObserver,
+                    Player,
+                ))
+                .id();
+            // indexed by the end of the frame
+            app.update();
+
+            Self {
+                app,
+                npc,
+                player,
+                next_to: default(),
+                away_from: default(),
+            }
+        }
+
+        // returns how many times the player got next to, and away from the NPC
+        fn walk(&mut self, xs: &[f32]) -> (usize, usize) {
+            let (mut next_to, mut away_from) = (0, 0);
+            for &x in xs {
+                self.app
+                    .world
+                    .get_mut::<Transform>(self.player)
+                    .unwrap()
+                    .translation
+                    .x = x;
+                self.app.update();
+
+                let world = &self.app.world;
+                next_to += self.next_to.iter(world.resource()).count();
+                away_from += self.away_from.iter(world.resource()).count();
+            }
+            (next_to, away_from)
+        }
+    }
+
+    // back and forth across 150, a frame on each side
+    fn jitter(times: usize) -> Vec<f32> {
+        [148., 152.].repeat(times)
+    }
+
+    #[test]
+    fn test_single_edge_flickers() {
+        let mut run = ProximityRun::new(ProximityRange {
+            enter: 150.,
+            exit: 150.,
+            dwell: Duration::ZERO,
+        });
+        assert_eq!(run.walk(&jitter(10)), (10, 10));
+    }
+
+    #[test]
+    fn test_hysteresis_stops_flicker() {
+        let mut run = ProximityRun::new(ProximityRange {
+            enter: 150.,
+            exit: 175.,
+            dwell: Duration::ZERO,
+        });
+        assert_eq!(run.walk(&jitter(10)), (1, 0));
+        assert_eq!(run.walk(&[174., 176., 174., 176.]), (0, 1));
+        assert_eq!(run.walk(&jitter(10)), (1, 0));
+    }
+
+    #[test]
+    fn test_dwell_stops_flicker() {
+        let mut run = ProximityRun::new(ProximityRange {
+            enter: 150.,
+            exit: 150.,
+            dwell: Duration::from_millis(100),
+        });
+        assert_eq!(run.walk(&jitter(10)), (0, 0));
+        // 16ms frames, 100ms is over on the 8th one
+        assert_eq!(run.walk(&[140.; 7]), (0, 0));
+        assert_eq!(run.walk(&[140.]), (1, 0));
+        assert_eq!(run.walk(&jitter(10)), (0, 0));
+        assert_eq!(run.walk(&[160.; 8]), (0, 1));
+    }
+
+    fn press(app: &mut App, action: Action) {
+        app.world.resource_mut::<Input<Action>>().press(action);
+        app.update();
+        let mut actions = app.world.resource_mut::<Input<Action>>();
+        actions.release(action);
+        actions.clear();
+    }
+
+    #[test]
+    fn test_interact_dispatches_by_kind() {
+        let mut flags = GameFlags::default();
+        flags.declare("clues", FlagValue::Int(0));
+
+        let mut app = App::new();
+        app.add_state(AppState::InGame)
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(InteractionTarget::default())
+            .insert_resource(Inventory::default())
+            .insert_resource(flags)
+            .add_event::<InteractEvent>()
+            .add_event::<NextToObjEvent>()
+            .add_event::<AwayFromObjEvent>()
+            .add_system(input_interact_trigger.label(Label::Interact))
+            .add_system(open_handler.after(Label::Interact))
+            .add_system(pick_up_handler.after(Label::Interact))
+            .add_system(next_to_npc_event_handler)
+            .add_system(away_from_npc_event_handler);
+
+        let door = app
+            .world
+            .spawn((
+                Interactable::new(InteractionKind::Open),
+                Openable::default(),
+            ))
+            .id();
+        let coin = app
+            .world
+            .spawn((
+                crate::Name::new("Cufflink"),
+                Interactable {
+                    kind: InteractionKind::PickUp,
+                    effects: vec![Effect::parse("clues += 1").unwrap()],
+                },
+            ))
+            .id();
+
+        // objects other than NPCs come and go without trouble
+        let observer = app.world.spawn_empty().id();
+        app.world.send_event(NextToObjEvent {
+            observer,
+            entity: door,
+        });
+        app.world.send_event(AwayFromObjEvent {
+            observer,
+            entity: door,
+        });
+        app.update();
+
+        app.world.resource_mut::<InteractionTarget>().value = vec![door, coin];
+        press(&mut app, Action::Interact);
+        assert!(app.world.get::<Openable>(door).unwrap().open);
+        press(&mut app, Action::Interact);
+        assert!(!app.world.get::<Openable>(door).unwrap().open);
+
+        app.world.resource_mut::<InteractionTarget>().value = vec![coin];
+        press(&mut app, Action::Interact);
+        assert!(app.world.get_entity(coin).is_none());
+        assert_eq!(app.world.resource::<Inventory>().items, ["Cufflink"]);
+        assert_eq!(
+            app.world.resource::<GameFlags>().get("clues"),
+            Ok(&FlagValue::Int(1))
+        );
+
+        // nothing targeted, nothing happens
+        app.world.resource_mut::<InteractionTarget>().value = vec![];
+        press(&mut app, Action::Interact);
+        assert!(!app.world.get::<Openable>(door).unwrap().open);
+    }
+
+    #[test]
+    fn test_talking_yields_to_pausing() {
+        let mut app = App::new();
+        app.add_state(AppState::InGame)
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(DialogLibrary::default())
+            .insert_resource(ActiveDialog::default())
+            .insert_resource(GameFlags::default())
+            .add_event::<InteractEvent>()
+            .add_system(input_pause_screen_trigger.label(Label::StateTriggers))
+            .add_system(talk_handler.after(Label::StateTriggers));
+        let joe = app.world.spawn((crate::Name::new("Joe"), NPC)).id();
+        app.update();
+
+        // walked up to him right as the game got paused
+        app.world.send_event(InteractEvent {
+            entity: joe,
+            kind: InteractionKind::Talk,
+        });
+        press(&mut app, Action::Pause);
+        assert_eq!(
+            app.world.resource::<State<AppState>>().current(),
+            &AppState::PauseScreen
+        );
+        assert!(app.world.resource::<ActiveDialog>().value.is_none());
+    }
+
+    #[test]
+    fn test_targets_only_faced_and_visible() {
+        let mut app = App::new();
+        app.insert_resource(ProximityToObjResource::default())
+            .insert_resource(InteractionTarget::default())
+            .add_event::<TargetChanged>()
+            .add_system(rank_interactables_in_proximity);
+
+        let player = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 0.),
+                Facing { value: Vec2::X },
+                Player,
+            ))
+            .id();
+        let cone = || FacingCone {
+            half_angle: std::f32::consts::FRAC_PI_4,
+        };
+        let ahead = app
+            .world
+            .spawn((
+                Transform::from_xy(100., 0.),
+                Interactable::new(InteractionKind::Talk),
+                cone(),
+            ))
+            .id();
+        let behind = app
+            .world
+            .spawn((
+                Transform::from_xy(-90., 0.),
+                Interactable::new(InteractionKind::Talk),
+                cone(),
+            ))
+            .id();
+        // needs no facing, but there's a wall in the way
+        let walled_off = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 80.),
+                Interactable::new(InteractionKind::Examine),
+            ))
+            .id();
+        let wall = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 40.),
+                Occluder {
+                    shape: Shape::rect(Vec2::new(200., 10.)),
+                },
+            ))
+            .id();
+
+        let range = ProximityRange {
+            enter: 150.,
+            exit: 150.,
+            dwell: Duration::ZERO,
+        };
+        for (entity, distance) in [(ahead, 100.), (behind, 90.), (walled_off, 80.)] {
+            let mut state = ProximityState::default();
+            state.update(distance, Duration::ZERO, &range);
+            app.world
+                .resource_mut::<ProximityToObjResource>()
+                .values
+                .entry(player)
+                .or_default()
+                .insert(entity, state);
+        }
+
+        app.update();
+        assert_eq!(app.world.resource::<InteractionTarget>().value, [ahead]);
+
+        app.world.get_mut::<Facing>(player).unwrap().value = Vec2::NEG_X;
+        app.update();
+        assert_eq!(app.world.resource::<InteractionTarget>().value, [behind]);
+
+        app.world.despawn(wall);
+        app.update();
+        assert_eq!(
+            app.world.resource::<InteractionTarget>().value,
+            [walled_off, behind]
+        );
+    }
+
+    #[test]
+    fn test_prompt_follows_target() {
+        let mut app = App::new();
+        app.insert_resource(InteractionTarget::default())
+            .insert_resource(InputMap::default())
+            .add_system(update_interaction_prompt);
+
+        let prompt = app
+            .world
+            .spawn((
+                Text::from_section("", default()),
+                Transform::default(),
+                Visibility { is_visible: false },
+                InteractionPrompt,
+            ))
+            .id();
+        let joe = app
+            .world
+            .spawn((
+                crate::Name::new("Joe"),
+                Interactable::new(InteractionKind::Talk),
+                Transform::from_xy(200., 0.),
+                Sprite {
+                    custom_size: Some(Vec2::new(100., 100.)),
+                    ..default()
+                },
+            ))
+            .id();
+        let text = |app: &App| {
+            app.world.get::<Text>(prompt).unwrap().sections[0]
+                .value
+                .clone()
+        };
+        let visible = |app: &App| app.world.get::<Visibility>(prompt).unwrap().is_visible;
+
+        app.update();
+        assert!(!visible(&app));
+
+        app.world.resource_mut::<InteractionTarget>().value = vec![joe];
+        app.update();
+        assert!(visible(&app));
+        assert_eq!(text(&app), "[E] Talk to Joe");
+
+        // above the sprite, wherever it goes
+        app.world.get_mut::<Transform>(joe).unwrap().translation.x = -50.;
+        app.update();
+        let at = app.world.get::<Transform>(prompt).unwrap().translation;
+        assert_eq!(at.x, -50.);
+        assert!(at.y > 50.);
+
+        app.world
+            .resource_mut::<InputMap>()
+            .rebind(Action::Interact, Binding::Key(KeyCode::Space))
+            .unwrap();
+        app.update();
+        assert_eq!(text(&app), "[Space] Talk to Joe");
+
+        app.world.despawn(joe);
+        app.update();
+        assert!(!visible(&app));
+    }
+
+    #[test]
+    fn test_despawn_in_range() {
+        let range = ProximityRange {
+            enter: 150.,
+            exit: 175.,
+            dwell: Duration::ZERO,
+        };
+        let mut run = ProximityRun::new(range);
+        assert_eq!(run.walk(&[100.]), (1, 0));
+        assert_eq!(
+            run.app.world.resource::<InteractionTarget>().value,
+            [run.npc]
+        );
+
+        // gone while the player stands right next to it, despawned by a handler's commands
+        run.app.world.send_event(InteractEvent {
+            entity: run.npc,
+            kind: InteractionKind::PickUp,
+        });
+        assert_eq!(run.walk(&[100., 100.]), (0, 1));
+        assert!(run.app.world.get_entity(run.npc).is_none());
+        assert!(run.app.world.resource::<ProximityGrid>().value.is_empty());
+        assert!(run
+            .app
+            .world
+            .resource::<ProximityToObjResource>()
+            .observed_by(run.player)
+            .next()
+            .is_none());
+        assert_eq!(run.app.world.resource::<InteractionTarget>().value, []);
+        assert_eq!(run.walk(&[100., 300., 100.]), (0, 0));
+
+        // about to enter, it's never been next to the player, nothing to tell
+        let mut run = ProximityRun::new(ProximityRange {
+            dwell: Duration::from_millis(100),
+            ..range
+        });
+        assert_eq!(run.walk(&[100., 100.]), (0, 0));
+        run.app.world.despawn(run.npc);
+        assert_eq!(run.walk(&[100.; 10]), (0, 0));
+        assert!(run
+            .app
+            .world
+            .resource::<ProximityToObjResource>()
+            .observed_by(run.player)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_stops_observing_in_range() {
+        let mut run = ProximityRun::new(ProximityRange {
+            enter: 150.,
+            exit: 175.,
+            dwell: Duration::ZERO,
+        });
+        assert_eq!(run.walk(&[100.]), (1, 0));
+
+        run.app.world.entity_mut(run.npc).remove::<InProximity>();
+        assert_eq!(run.walk(&[100.]), (0, 1));
+        assert_eq!(run.app.world.resource::<InteractionTarget>().value, []);
+
+        // and again once it's back
+        run.app.world.entity_mut(run.npc).insert(InProximity {
+            range: ProximityRange {
+                enter: 150.,
+                exit: 175.,
+                dwell: Duration::ZERO,
+            },
+        });
+        // indexed at the end of the frame, next to the player on the one after
+        assert_eq!(run.walk(&[100., 100.]), (1, 0));
+    }
+
+    #[test]
+    fn test_observers_track_separately() {
+        let range = ProximityRange {
+            enter: 50.,
+            exit: 50.,
+            dwell: Duration::ZERO,
+        };
+        let mut app = App::new();
+        app.insert_resource(Timestep::default())
+            .insert_resource(ProximityToObjResource::default())
+            .insert_resource(ProximityGrid::default())
+            .add_event::<NextToObjEvent>()
+            .add_event::<AwayFromObjEvent>()
+            .add_system(index_proximity_objects.before(next_to_obj_watcher))
+            .add_system(next_to_obj_watcher);
+        let mut next_to = ManualEventReader::<NextToObjEvent>::default();
+        let mut entered = |app: &mut App| {
+            app.update();
+            let mut pairs: Vec<_> = next_to
+                .iter(app.world.resource())
+                .map(|ev| (ev.observer, ev.entity))
+                .collect();
+            pairs.sort();
+            pairs
+        };
+
+        // nobody observes, nothing to measure from
+        let npc = app
+            .world
+            .spawn((Transform::from_xy(0., 0.), InProximity { range }))
+            .id();
+        assert_eq!(entered(&mut app), []);
+
+        // the NPC notices a companion, who notices the NPC back
+        let companion = app
+            .world
+            .spawn((
+                Transform::from_xy(30., 0.),
+                InProximity { range },
+                ProximityObserver,
+            ))
+            .id();
+        app.world.entity_mut(npc).insert(ProximityObserver);
+        let far = app
+            .world
+            .spawn((Transform::from_xy(500., 0.), ProximityObserver))
+            .id();
+        assert_eq!(entered(&mut app), [(npc, companion), (companion, npc)]);
+
+        let proximity = app.world.resource::<ProximityToObjResource>();
+        assert_eq!(proximity.observed_by(npc).count(), 1);
+        assert_eq!(proximity.observed_by(far).count(), 0);
+
+        app.world.get_mut::<Transform>(far).unwrap().translation.x = 40.;
+        assert_eq!(entered(&mut app), [(far, npc), (far, companion)]);
+
+        // forgotten along with the observer
+        app.world.despawn(far);
+        app.update();
+        assert!(!app
+            .world
+            .resource::<ProximityToObjResource>()
+            .values
+            .contains_key(&far));
+    }
+
+    #[test]
+    fn test_player_stops_at_walls() {
+        let mut app = App::new();
+        app.insert_resource(Timestep::new(Duration::from_secs(1)))
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(Controllers::default())
+            .insert_resource(StickSettings::default())
+            .insert_resource(MovementSettings::default())
+            .add_system(player_movement);
+        let player = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 0.),
+                Velocity::default(),
+                Facing::default(),
+                KinematicBody {
+                    shape: Shape::Circle { radius: 50. },
+                },
+                Player,
+            ))
+            .id();
+        app.world.spawn((
+            Transform::from_xy(200., 0.),
+            Collider {
+                shape: Shape::rect(Vec2::new(20., 400.)),
+            },
+        ));
+
+        // a whole second in a single tick, enough to go right through the wall
+        app.world
+            .resource_mut::<Input<Action>>()
+            .press(Action::MoveRight);
+        app.update();
+        let at = app.world.get::<Transform>(player).unwrap().translation;
+        assert!((at.x - 140.).abs() < 0.1, "{}", at.x);
+
+        // and along it
+        app.world
+            .resource_mut::<Input<Action>>()
+            .press(Action::MoveUp);
+        app.update();
+        let at = app.world.get::<Transform>(player).unwrap().translation;
+        assert!((at.x - 140.).abs() < 0.1, "{}", at.x);
+        assert!(at.y > 0.);
+    }
+
+    #[test]
+    fn test_gamepad() {
+        let mut app = App::new();
+        app.add_state(AppState::InGame)
+            .insert_resource(Timestep::new(Duration::from_secs(1)))
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(Input::<MouseButton>::default())
+            .insert_resource(InputMap::default())
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(Controllers::default())
+            .insert_resource(StickSettings { deadzone: 0.2 })
+            .insert_resource(MovementSettings::default())
+            .add_event::<GamepadEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, read_gamepads)
+            .add_system_to_stage(CoreStage::PreUpdate, read_actions.after(read_gamepads))
+            .add_system(player_movement)
+            .add_system(input_pause_screen_trigger)
+            .add_system(input_main_menu_trigger)
+            .add_system(input_back_trigger);
+        let player = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 0.),
+                Velocity::default(),
+                Facing::default(),
+                KinematicBody {
+                    shape: Shape::Circle { radius: 50. },
+                },
+                Player,
+            ))
+            .id();
+
+        let pad = Gamepad::new(0);
+        // a second long tick after the events
+        let frame = |app: &mut App, events: &[GamepadEventType]| {
+            for event_type in events {
+                app.world
+                    .send_event(GamepadEvent::new(pad, event_type.clone()));
+            }
+            app.update();
+            app.world.get::<Transform>(player).unwrap().translation
+        };
+        let connected = GamepadEventType::Connected(GamepadInfo {
+            name: "Testing pad".into(),
+        });
+        let stick_x = |value| GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, value);
+        let button = |button_type, value| GamepadEventType::ButtonChanged(button_type, value);
+
+        // halfway between the deadzone and full tilt is half the speed
+        let at = frame(&mut app, &[connected.clone(), stick_x(0.6)]);
+        assert!((at.x - 125.).abs() < 0.1, "{}", at.x);
+        assert_eq!(app.world.get::<Facing>(player).unwrap().value, Vec2::X);
+
+        // within the deadzone, standing still
+        let at = frame(&mut app, &[stick_x(-0.1)]);
+        assert!((at.x - 125.).abs() < 0.1, "{}", at.x);
+
+        let at = frame(&mut app, &[button(GamepadButtonType::South, 1.)]);
+        assert!(app
+            .world
+            .resource::<Input<Action>>()
+            .just_pressed(Action::Interact));
+        assert!((at.x - 125.).abs() < 0.1, "{}", at.x);
+
+        frame(
+            &mut app,
+            &[
+                button(GamepadButtonType::South, 0.),
+                button(GamepadButtonType::Start, 1.),
+            ],
+        );
+        assert_eq!(
+            app.world.resource::<State<AppState>>().current(),
+            &AppState::PauseScreen
+        );
+        frame(
+            &mut app,
+            &[
+                button(GamepadButtonType::Start, 0.),
+                button(GamepadButtonType::East, 1.),
+            ],
+        );
+        assert_eq!(
+            app.world.resource::<State<AppState>>().current(),
+            &AppState::InGame
+        );
+
+        // out to the main menu and back, without a keyboard
+        let state = |app: &App| app.world.resource::<State<AppState>>().current().clone();
+        frame(
+            &mut app,
+            &[
+                button(GamepadButtonType::East, 0.),
+                button(GamepadButtonType::Start, 1.),
+            ],
+        );
+        assert_eq!(state(&app), AppState::PauseScreen);
+        frame(
+            &mut app,
+            &[
+                button(GamepadButtonType::Start, 0.),
+                button(GamepadButtonType::Select, 1.),
+            ],
+        );
+        assert_eq!(state(&app), AppState::MainMenu);
+        frame(
+            &mut app,
+            &[
+                button(GamepadButtonType::Select, 0.),
+                button(GamepadButtonType::East, 1.),
+            ],
+        );
+        assert_eq!(state(&app), AppState::InGame);
+        frame(&mut app, &[button(GamepadButtonType::East, 0.)]);
+
+        // unplugged halfway through walking, doesn't keep going on its own
+        frame(&mut app, &[stick_x(1.)]);
+        let at = frame(&mut app, &[GamepadEventType::Disconnected]);
+        let unplugged = frame(&mut app, &[]);
+        assert_eq!(at, unplugged);
+        assert!(!app.world.resource::<Controllers>().is_connected(pad));
+
+        // and back again
+        let at = frame(&mut app, &[connected, stick_x(-1.)]);
+        assert!(at.x < unplugged.x);
+    }
+
+    // the player sprinting up and right along a wall, frames taking as long as `frames`
+    fn walk_along_wall(frames: &[Duration]) -> App {
+        let mut app = App::new();
+        app.add_state(AppState::InGame)
+            .insert_resource(Time::default())
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(Controllers::default())
+            .insert_resource(StickSettings::default())
+            .insert_resource(MovementSettings::default())
+            .add_plugin(GameplayTicksPlugin)
+            .add_system_set_to_stage(
+                GameplayStage,
+                SystemSet::new()
+                    .with_run_criteria(in_game)
+                    .label(Label::Gameplay)
+                    .after(Label::TickStart)
+                    .with_system(player_movement),
+            );
+        app.world.spawn((
+            Transform::from_xy(0., 0.),
+            Velocity::default(),
+            Interpolated::default(),
+            Facing::default(),
+            KinematicBody {
+                shape: Shape::Circle { radius: 50. },
+            },
+            Player,
+        ));
+        app.world.spawn((
+            Transform::from_xy(200., 0.),
+            Collider {
+                shape: Shape::rect(Vec2::new(20., 2000.)),
+            },
+        ));
+
+        let mut now = app.world.resource::<Time>().startup();
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        let mut actions = app.world.resource_mut::<Input<Action>>();
+        actions.press(Action::MoveRight);
+        actions.press(Action::MoveUp);
+        actions.press(Action::Sprint);
+        for &frame in frames {
+            now += frame;
+            app.world.resource_mut::<Time>().update_with_instant(now);
+            app.update();
+        }
+        app
+    }
+
+    fn ticked_position(app: &mut App) -> Vec2 {
+        app.world
+            .query_filtered::<&Interpolated, With<Player>>()
+            .single(&app.world)
+            .current
+    }
+
+    #[test]
+    fn test_same_positions_at_any_frame_rate() {
+        let step = Timestep::default().step();
+        // 120 ticks either way
+        let mut even = walk_along_wall(&vec![step; 120]);
+        let mut uneven =
+            walk_along_wall(&[step / 2, step * 2, step / 2, Duration::ZERO, step].repeat(30));
+
+        let position = ticked_position(&mut even);
+        assert_eq!(position, ticked_position(&mut uneven));
+        // stopped by the wall, and went along it
+        assert!((position.x - 140.).abs() < 0.1, "{}", position);
+        assert!(position.y > 100., "{}", position);
+    }
+
+    #[test]
+    fn test_drawn_in_between_ticks() {
+        let step = Timestep::default().step();
+        let mut app = walk_along_wall(&[step; 10]);
+        let ticked = ticked_position(&mut app);
+
+        // halfway to the next tick
+        let mut time = app.world.resource_mut::<Time>();
+        let now = time.last_update().unwrap() + step / 2;
+        time.update_with_instant(now);
+        app.update();
+        let (transform, interpolated) = app
+            .world
+            .query_filtered::<(&Transform, &Interpolated), With<Player>>()
+            .single(&app.world);
+        assert_eq!(interpolated.current, ticked);
+        let drawn_at = transform.translation.truncate();
+        assert!(drawn_at.distance(interpolated.previous.lerp(ticked, 0.5)) < 1e-3);
+        assert_ne!(drawn_at, ticked);
+    }
+
+    // a player ticking along routes, with a wall between it and whatever's clicked
+    fn clicking_around_wall() -> (App, Entity) {
+        let mut app = App::new();
+        app.insert_resource(Timestep::default())
+            .insert_resource(Input::<Action>::default())
+            .insert_resource(Controllers::default())
+            .insert_resource(StickSettings::default())
+            .insert_resource(MovementSettings::default())
+            .insert_resource(NavigationGrid::default())
+            .add_event::<MoveToEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, update_navigation_grid)
+            .add_system(move_to_handler.before(player_movement))
+            .add_system(player_movement);
+        let player = app
+            .world
+            .spawn((
+                Transform::from_xy(-300., 0.),
+                Velocity::default(),
+                Facing::default(),
+                KinematicBody {
+                    shape: Shape::Circle { radius: 20. },
+                },
+                ProximityShape {
+                    value: Shape::Circle { radius: 20. },
+                },
+                Player,
+            ))
+            .id();
+        app.world.spawn((
+            Transform::from_xy(-150., 0.),
+            Collider {
+                shape: Shape::rect(Vec2::new(20., 300.)),
+            },
+        ));
+        // mapped by the end of the frame
+        app.update();
+        (app, player)
+    }
+
+    #[test]
+    fn test_click_to_move() {
+        let (mut app, player) = clicking_around_wall();
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(0., 0.),
+        });
+        app.update();
+        assert!(app.world.get::<Route>(player).is_some());
+
+        // ten seconds is plenty to get around
+        let mut furthest_up = 0_f32;
+        for _ in 0..600 {
+            app.update();
+            let at = app.world.get::<Transform>(player).unwrap().translation;
+            furthest_up = furthest_up.max(at.y.abs());
+        }
+        let at = app.world.get::<Transform>(player).unwrap().translation;
+        assert!(at.truncate().distance(Vec2::ZERO) < 5., "{}", at);
+        assert!(furthest_up > 150., "went through the wall");
+        assert!(app.world.get::<Route>(player).is_none());
+
+        // nowhere to go inside the wall, and the keys take over from a route
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(-150., 0.),
+        });
+        app.update();
+        assert!(app.world.get::<Route>(player).is_none());
+        // nor off the level, until there's something there
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(500., 0.),
+        });
+        app.update();
+        assert!(app.world.get::<Route>(player).is_none());
+        app.world.spawn((
+            Transform::from_xy(500., 0.),
+            InProximity {
+                range: ProximityRange {
+                    enter: 50.,
+                    exit: 75.,
+                    dwell: Duration::ZERO,
+                },
+            },
+        ));
+        app.update();
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(500., 0.),
+        });
+        app.update();
+        assert!(app.world.get::<Route>(player).is_some());
+        app.world
+            .resource_mut::<Input<Action>>()
+            .press(Action::MoveUp);
+        app.update();
+        assert!(app.world.get::<Route>(player).is_none());
+    }
+
+    #[test]
+    fn test_click_through_opened_door() {
+        let (mut app, player) = clicking_around_wall();
+        app.add_event::<InteractEvent>().add_system(open_handler);
+        // a room with the door on the left, the only way in
+        for (x, y, size) in [
+            (100., 70., Vec2::new(160., 20.)),
+            (100., -70., Vec2::new(160., 20.)),
+            (170., 0., Vec2::new(20., 160.)),
+        ] {
+            app.world.spawn((
+                Transform::from_xy(x, y),
+                Collider {
+                    shape: Shape::rect(size),
+                },
+            ));
+        }
+        let sprite = Sprite {
+            custom_size: Some(Vec2::new(20., 160.)),
+            ..default()
+        };
+        let door = app
+            .world
+            .spawn((
+                Transform::from_xy(30., 0.),
+                Collider::from_sprite(&sprite),
+                sprite,
+                Openable::default(),
+            ))
+            .id();
+        app.update();
+
+        let click = |app: &mut App| {
+            app.world.send_event(MoveToEvent {
+                position: Vec2::new(100., 0.),
+            });
+            app.update();
+            app.world.get::<Route>(player).is_some()
+        };
+        assert!(!click(&mut app), "walked through a closed door");
+
+        // opened like by pressing E, the collider goes with the handler's commands
+        app.world.send_event(InteractEvent {
+            entity: door,
+            kind: InteractionKind::Open,
+        });
+        app.update();
+        assert!(app.world.get::<Collider>(door).is_none());
+        assert!(click(&mut app));
+    }
+
+    #[test]
+    fn test_click_on_npc() {
+        let (mut app, player) = clicking_around_wall();
+        app.add_state(AppState::InGame)
+            .insert_resource(ProximityToObjResource::default())
+            .insert_resource(ProximityGrid::default())
+            .insert_resource(InteractionTarget::default())
+            .insert_resource(GameFlags::default())
+            .add_event::<NextToObjEvent>()
+            .add_event::<AwayFromObjEvent>()
+            .add_event::<TargetChanged>()
+            .add_event::<InteractEvent>()
+            .add_system(index_proximity_objects.before(next_to_obj_watcher))
+            .add_system(
+                next_to_obj_watcher
+                    .after(player_movement)
+                    .before(rank_interactables_in_proximity),
+            )
+            .add_system(rank_interactables_in_proximity)
+            .add_system(route_interaction.after(rank_interactables_in_proximity));
+        app.world.entity_mut(player).insert(ProximityObserver);
+        let npc = app
+            .world
+            .spawn((
+                Transform::from_xy(0., 0.),
+                Collider {
+                    shape: Shape::Circle { radius: 20. },
+                },
+                ProximityShape {
+                    value: Shape::Circle { radius: 20. },
+                },
+                InProximity {
+                    range: ProximityRange {
+                        enter: 50.,
+                        exit: 75.,
+                        dwell: Duration::ZERO,
+                    },
+                },
+                Interactable::new(InteractionKind::Talk),
+                FacingCone {
+                    half_angle: std::f32::consts::FRAC_PI_4,
+                },
+                NPC,
+            ))
+            .id();
+        // boxed in but for the right, right outside is near enough but they'd never be seen
+        for (x, y, size) in [
+            (-50., 0., Vec2::new(4., 140.)),
+            (0., 70., Vec2::new(100., 4.)),
+            (0., -70., Vec2::new(100., 4.)),
+        ] {
+            app.world.spawn((
+                Transform::from_xy(x, y),
+                Occluder {
+                    shape: Shape::rect(size),
+                },
+            ));
+        }
+
+        let mut reader = ManualEventReader::<InteractEvent>::default();
+        let mut walk = |app: &mut App, frames| {
+            let mut talked = vec![];
+            for _ in 0..frames {
+                app.update();
+                talked.extend(
+                    reader
+                        .iter(app.world.resource())
+                        .map(|ev| (ev.entity, ev.kind)),
+                );
+            }
+            talked
+        };
+        app.update();
+        // anywhere on them
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(10., 5.),
+        });
+        assert_eq!(walk(&mut app, 600), [(npc, InteractionKind::Talk)]);
+        assert!(app.world.get::<Route>(player).is_none());
+        let at = app.world.get::<Transform>(player).unwrap().translation;
+        let distance = at.truncate().length();
+        // in range, without walking into them
+        assert!((40.0..90.).contains(&distance), "{}", at);
+        assert!(at.x > -50. && at.y.abs() < 70., "{}", at);
+
+        // never acquired, not waiting on them forever
+        app.world.entity_mut(player).remove::<ProximityObserver>();
+        app.world.send_event(MoveToEvent {
+            position: Vec2::new(10., 5.),
+        });
+        assert_eq!(walk(&mut app, 60), []);
+        assert!(app.world.get::<Route>(player).is_none());
+    }
 }
@@ -0,0 +1,230 @@
+// How the player speeds up and slows down, rather than starting and stopping on the spot.
+//
+// Tuned in assets/movement.ron, anything left out of it keeps its default.
+
+use bevy::math::Vec2;
+use bevy::prelude::Resource;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+// how close to the end of a path counts as being there
+const ARRIVED: f32 = 2.;
+
+// speeds in units per second, rates in units per second squared
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MovementSettings {
+    pub max_speed: f32,
+    // how quickly it gets up to speed
+    pub acceleration: f32,
+    // how quickly it stops once let go, or slows down to a slower speed
+    pub deceleration: f32,
+    // top speed while sprinting, none to not sprint at all
+    pub sprint_speed: Option<f32>,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            max_speed: 250.,
+            acceleration: 2000.,
+            deceleration: 2500.,
+            sprint_speed: Some(400.),
+        }
+    }
+}
+
+impl MovementSettings {
+    pub fn default_path() -> PathBuf {
+        bevy::asset::FileAssetIo::get_base_path()
+            .join("assets")
+            .join("movement.ron")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        ron::from_str(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn top_speed(&self, sprinting: bool) -> f32 {
+        match self.sprint_speed {
+            Some(sprint_speed) if sprinting => sprint_speed,
+            _ => self.max_speed,
+        }
+    }
+}
+
+// Velocity after `delta` seconds of pushing towards `input`.
+// The input is a direction, with a length up to 1 for how far to push. Longer ones,
+// like two keys held at once, get cut down, so going diagonally isn't any faster.
+pub fn steer(
+    velocity: Vec2,
+    input: Vec2,
+    sprinting: bool,
+    settings: &MovementSettings,
+    delta: f32,
+) -> Vec2 {
+    let target = input.clamp_length_max(1.) * settings.top_speed(sprinting);
+    let rate = if target.length_squared() >= velocity.length_squared() {
+        settings.acceleration
+    } else {
+        settings.deceleration
+    };
+
+    let change = target - velocity;
+    let max_change = rate * delta;
+    if change.length() <= max_change {
+        target
+    } else {
+        velocity + change.normalize() * max_change
+    }
+}
+
+// Input walking along `waypoints`, dropping them once within `pass_within`.
+// It slows down to stop at the last one, and only there.
+pub fn follow(
+    waypoints: &mut VecDeque<Vec2>,
+    position: Vec2,
+    pass_within: f32,
+    settings: &MovementSettings,
+) -> Vec2 {
+    while let Some(&next) = waypoints.front() {
+        let within = if waypoints.len() == 1 {
+            ARRIVED
+        } else {
+            pass_within
+        };
+        if position.distance(next) > within {
+            break;
+        }
+        waypoints.pop_front();
+    }
+
+    let to_next = match waypoints.front() {
+        Some(&next) => next - position,
+        None => return Vec2::ZERO,
+    };
+    if waypoints.len() > 1 {
+        return to_next.normalize();
+    }
+    // from walking speed
+    let braking_distance = settings.max_speed.powi(2) / (2. * settings.deceleration);
+    to_next / braking_distance.max(to_next.length())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = 1. / 60.;
+
+    fn settings() -> MovementSettings {
+        MovementSettings {
+            max_speed: 100.,
+            acceleration: 600.,
+            deceleration: 1200.,
+            sprint_speed: Some(200.),
+        }
+    }
+
+    // frames of holding `input` down, from standing still
+    fn hold(input: Vec2, sprinting: bool, settings: &MovementSettings, frames: usize) -> Vec2 {
+        (0..frames).fold(Vec2::ZERO, |velocity, _| {
+            steer(velocity, input, sprinting, settings, FRAME)
+        })
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 1e-3,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_speeds_up_and_slows_down() {
+        let settings = settings();
+        // a sixth of a second to get up to speed
+        assert_close(hold(Vec2::X, false, &settings, 5), Vec2::new(50., 0.));
+        assert_close(hold(Vec2::X, false, &settings, 10), Vec2::new(100., 0.));
+        assert_close(hold(Vec2::X, false, &settings, 60), Vec2::new(100., 0.));
+
+        // and half that to stop
+        let let_go = steer(Vec2::new(100., 0.), Vec2::ZERO, false, &settings, FRAME);
+        assert_close(let_go, Vec2::new(80., 0.));
+        assert_close(
+            (0..5).fold(let_go, |velocity, _| {
+                steer(velocity, Vec2::ZERO, false, &settings, FRAME)
+            }),
+            Vec2::ZERO,
+        );
+    }
+
+    #[test]
+    fn test_diagonals_are_not_faster() {
+        let settings = settings();
+        let diagonal = hold(Vec2::new(1., 1.), false, &settings, 60);
+        assert!((diagonal.length() - 100.).abs() < 1e-3);
+        assert!((diagonal.x - diagonal.y).abs() < 1e-3);
+
+        // a stick tilted part way is walking slower
+        assert_close(
+            hold(Vec2::new(0., -0.5), false, &settings, 60),
+            Vec2::new(0., -50.),
+        );
+    }
+
+    #[test]
+    fn test_sprint() {
+        let mut settings = settings();
+        assert_close(hold(Vec2::X, true, &settings, 60), Vec2::new(200., 0.));
+        // letting go of sprint slows down to walking
+        let walking = steer(Vec2::new(200., 0.), Vec2::X, false, &settings, FRAME);
+        assert_close(walking, Vec2::new(180., 0.));
+
+        settings.sprint_speed = None;
+        assert_close(hold(Vec2::X, true, &settings, 60), Vec2::new(100., 0.));
+    }
+
+    #[test]
+    fn test_load() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        MovementSettings::load(assets.join("movement.ron")).unwrap();
+
+        let partial: MovementSettings = ron::from_str("(max_speed: 300.)").unwrap();
+        assert_eq!(
+            partial,
+            MovementSettings {
+                max_speed: 300.,
+                ..MovementSettings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_follow() {
+        let settings = settings();
+        let corner = Vec2::new(100., 0.);
+        let end = Vec2::new(100., 100.);
+        let mut waypoints = VecDeque::from([corner, end]);
+        let (mut position, mut velocity) = (Vec2::ZERO, Vec2::ZERO);
+        let mut closest_to_corner = f32::MAX;
+        for _ in 0..600 {
+            let input = follow(&mut waypoints, position, 10., &settings);
+            velocity = steer(velocity, input, false, &settings, FRAME);
+            position += velocity * FRAME;
+            closest_to_corner = closest_to_corner.min(position.distance(corner));
+        }
+
+        assert!(waypoints.is_empty());
+        assert!(position.distance(end) <= ARRIVED, "{}", position);
+        assert!(velocity.length() < 1., "{}", velocity);
+        assert!(closest_to_corner <= 10.);
+        assert_eq!(follow(&mut waypoints, position, 10., &settings), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,345 @@
+// Ways around colliders, found with A* on a grid over the level.
+//
+// A cell is walkable when a body centered on it doesn't overlap any collider.
+// Paths go from cell center to cell center, diagonally only where both cells
+// along the diagonal are walkable too, so that they never cut a corner.
+
+use crate::shape::{gap, Shape};
+use bevy::math::{IVec2, Vec2};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+const NEIGHBORS: [(IVec2, f32); 8] = [
+    (IVec2::new(1, 0), 1.),
+    (IVec2::new(-1, 0), 1.),
+    (IVec2::new(0, 1), 1.),
+    (IVec2::new(0, -1), 1.),
+    (IVec2::new(1, 1), SQRT_2),
+    (IVec2::new(-1, 1), SQRT_2),
+    (IVec2::new(1, -1), SQRT_2),
+    (IVec2::new(-1, -1), SQRT_2),
+];
+
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+    cell_size: f32,
+    // corner of the first cell
+    min: Vec2,
+    size: IVec2,
+    walkable: Vec<bool>,
+}
+
+impl NavGrid {
+    // covers everything from `min` to `max`, walkable all over
+    pub fn new(min: Vec2, max: Vec2, cell_size: f32) -> Self {
+        assert!(cell_size > 0., "cell size must be positive");
+        let size = ((max - min) / cell_size).ceil().as_ivec2().max(IVec2::ZERO);
+        Self {
+            cell_size,
+            min,
+            size,
+            walkable: vec![true; (size.x * size.y) as usize],
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    // cells `body` can't be centered on without overlapping the collider
+    pub fn block(&mut self, body: &Shape, collider: &Shape, at: Vec2) {
+        if self.walkable.is_empty() {
+            return;
+        }
+        let reach = Vec2::splat(body.bounding_radius() + collider.bounding_radius());
+        // any part of it off the grid is left out
+        let (min, max) = (self.cell_clamped(at - reach), self.cell_clamped(at + reach));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                if gap(body, self.center(cell), collider, at) <= 0. {
+                    let index = self.index(cell).unwrap();
+                    self.walkable[index] = false;
+                }
+            }
+        }
+    }
+
+    fn cell_clamped(&self, position: Vec2) -> IVec2 {
+        ((position - self.min) / self.cell_size)
+            .floor()
+            .as_ivec2()
+            .clamp(IVec2::ZERO, self.size - 1)
+    }
+
+    // none off the grid
+    pub fn cell(&self, position: Vec2) -> Option<IVec2> {
+        let cell = ((position - self.min) / self.cell_size).floor().as_ivec2();
+        self.index(cell).map(|_| cell)
+    }
+
+    pub fn center(&self, cell: IVec2) -> Vec2 {
+        self.min + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    pub fn is_walkable(&self, position: Vec2) -> bool {
+        self.cell(position)
+            .is_some_and(|cell| self.is_walkable_cell(cell))
+    }
+
+    fn is_walkable_cell(&self, cell: IVec2) -> bool {
+        match self.index(cell) {
+            Some(index) => self.walkable[index],
+            None => false,
+        }
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let inside = cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all();
+        inside.then(|| (cell.y * self.size.x + cell.x) as usize)
+    }
+
+    fn cell_at(&self, index: usize) -> IVec2 {
+        let index = index as i32;
+        IVec2::new(index % self.size.x, index / self.size.x)
+    }
+
+    // Waypoints from `from` right to `to`, none when it's off the grid or not walkable.
+    pub fn path_to(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let goal = self.cell(to)?;
+        if !self.is_walkable_cell(goal) {
+            return None;
+        }
+        let mut path = self.find_path(from, to, |center| self.cell(center) == Some(goal))?;
+        // the point itself rather than the center of its cell
+        match path.last_mut() {
+            Some(last) => *last = to,
+            None => path.push(to),
+        }
+        Some(path)
+    }
+
+    // Waypoints from `from` to the nearest cell center `arrived` accepts, searching
+    // towards `toward`. Empty when it's there already, none when it can't get there.
+    pub fn find_path(
+        &self,
+        from: Vec2,
+        toward: Vec2,
+        arrived: impl Fn(Vec2) -> bool,
+    ) -> Option<Vec<Vec2>> {
+        let start = self.index(self.cell(from)?)?;
+        let estimate = |cell: IVec2| self.center(cell).distance(toward);
+
+        let mut cost = vec![f32::INFINITY; self.walkable.len()];
+        let mut came_from: Vec<Option<usize>> = vec![None; self.walkable.len()];
+        let mut closed = vec![false; self.walkable.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0.;
+        open.push(Open {
+            estimate: estimate(self.cell_at(start)),
+            index: start,
+        });
+
+        while let Some(Open { index, .. }) = open.pop() {
+            if closed[index] {
+                continue;
+            }
+            closed[index] = true;
+            let cell = self.cell_at(index);
+            if arrived(self.center(cell)) {
+                return Some(self.trace(&came_from, index));
+            }
+
+            for (offset, distance) in NEIGHBORS {
+                let next = cell + offset;
+                let next_index = match self.index(next) {
+                    Some(next_index) if self.walkable[next_index] => next_index,
+                    _ => continue,
+                };
+                let diagonal = offset.x != 0 && offset.y != 0;
+                if diagonal
+                    && !(self.is_walkable_cell(cell + IVec2::new(offset.x, 0))
+                        && self.is_walkable_cell(cell + IVec2::new(0, offset.y)))
+                {
+                    continue;
+                }
+                let next_cost = cost[index] + distance * self.cell_size;
+                if next_cost < cost[next_index] {
+                    cost[next_index] = next_cost;
+                    came_from[next_index] = Some(index);
+                    open.push(Open {
+                        estimate: next_cost + estimate(next),
+                        index: next_index,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // centers of the cells where the path turns, and the last one, start excluded
+    fn trace(&self, came_from: &[Option<usize>], goal: usize) -> Vec<Vec2> {
+        let mut cells = vec![self.cell_at(goal)];
+        let mut index = goal;
+        while let Some(previous) = came_from[index] {
+            cells.push(self.cell_at(previous));
+            index = previous;
+        }
+        cells.reverse();
+
+        let turns = cells.windows(3).filter_map(|cells| {
+            let turned = cells[1] - cells[0] != cells[2] - cells[1];
+            turned.then_some(cells[1])
+        });
+        let last = cells.last().copied().filter(|_| cells.len() > 1);
+        turns.chain(last).map(|cell| self.center(cell)).collect()
+    }
+}
+
+// cells to search, the one estimated to be on the shortest path first
+#[derive(Debug, Clone, Copy)]
+struct Open {
+    estimate: f32,
+    index: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// reversed, the heap pops the greatest
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: Shape = Shape::Circle { radius: 10. };
+
+    // 400 by 400 around the origin, a wall down the middle
+    fn walled() -> NavGrid {
+        let mut grid = NavGrid::new(Vec2::splat(-200.), Vec2::splat(200.), 10.);
+        grid.block(
+            &BODY,
+            &Shape::rect(Vec2::new(20., 300.)),
+            Vec2::new(0., -50.),
+        );
+        grid
+    }
+
+    #[test]
+    fn test_straight_ahead() {
+        let grid = NavGrid::new(Vec2::splat(-200.), Vec2::splat(200.), 10.);
+        let to = Vec2::new(150., 3.);
+        // a single turn at most, to line up with the point
+        let path = grid.path_to(Vec2::new(-150., 0.), to).unwrap();
+        assert!(path.len() <= 2, "{:?}", path);
+        assert_eq!(path.last(), Some(&to));
+
+        // in the same cell
+        assert_eq!(
+            grid.path_to(Vec2::new(1., 1.), Vec2::new(2., 2.)),
+            Some(vec![Vec2::new(2., 2.)])
+        );
+    }
+
+    #[test]
+    fn test_around_walls() {
+        let grid = walled();
+        assert!(!grid.is_walkable(Vec2::ZERO));
+        // right beside it, a body there would overlap it
+        assert!(!grid.is_walkable(Vec2::new(15., 0.)));
+        assert!(grid.is_walkable(Vec2::new(25., 0.)));
+
+        let from = Vec2::new(-100., 0.);
+        let to = Vec2::new(100., 0.);
+        let path = grid.path_to(from, to).unwrap();
+        assert_eq!(path.last(), Some(&to));
+        // over the top of the wall, which ends at 100
+        assert!(path.iter().any(|waypoint| waypoint.y > 100.), "{:?}", path);
+        // never through it
+        let mut previous = from;
+        for &waypoint in &path {
+            let steps = (previous.distance(waypoint) / 2.).ceil() as usize;
+            for step in 0..=steps {
+                let at = previous.lerp(waypoint, step as f32 / steps.max(1) as f32);
+                assert!(
+                    gap(
+                        &BODY,
+                        at,
+                        &Shape::rect(Vec2::new(20., 300.)),
+                        Vec2::new(0., -50.)
+                    ) > 0.
+                );
+            }
+            previous = waypoint;
+        }
+    }
+
+    #[test]
+    fn test_no_way() {
+        let mut grid = walled();
+        // into the wall, and off the grid
+        assert_eq!(grid.path_to(Vec2::new(-100., 0.), Vec2::new(0., 0.)), None);
+        assert_eq!(
+            grid.path_to(Vec2::new(-100., 0.), Vec2::new(500., 0.)),
+            None
+        );
+
+        // walled in all around
+        grid.block(
+            &BODY,
+            &Shape::rect(Vec2::new(400., 20.)),
+            Vec2::new(0., 110.),
+        );
+        assert_eq!(
+            grid.path_to(Vec2::new(-100., 0.), Vec2::new(100., 0.)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_no_cutting_corners() {
+        let mut grid = NavGrid::new(Vec2::ZERO, Vec2::splat(30.), 10.);
+        // two blocks touching at a corner, the diagonal between them is too tight
+        let point = Shape::Point;
+        grid.block(&point, &Shape::rect(Vec2::splat(2.)), Vec2::new(15., 5.));
+        grid.block(&point, &Shape::rect(Vec2::splat(2.)), Vec2::new(5., 15.));
+        assert_eq!(grid.path_to(Vec2::new(5., 5.), Vec2::new(15., 15.)), None);
+    }
+
+    #[test]
+    fn test_near_enough() {
+        let grid = walled();
+        let npc = Shape::Circle { radius: 20. };
+        let npc_at = Vec2::new(100., 0.);
+        // anywhere within 30 of the NPC will do
+        let near = |center: Vec2| gap(&BODY, center, &npc, npc_at) < 30.;
+
+        let path = grid.find_path(Vec2::new(-100., 0.), npc_at, near).unwrap();
+        assert!(near(*path.last().unwrap()));
+        // already there
+        assert_eq!(
+            grid.find_path(Vec2::new(60., 0.), npc_at, near),
+            Some(vec![])
+        );
+    }
+}
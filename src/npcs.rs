@@ -0,0 +1,27 @@
+// NPCs placed in the level. Kept as data, so that content tools know who is
+// around to be talked to.
+
+pub struct NpcSpawn {
+    pub name: &'static str,
+    pub position: (f32, f32),
+    // condition on story progress for the NPC to show up
+    pub condition: Option<&'static str>,
+}
+
+pub const NPCS: &[NpcSpawn] = &[
+    NpcSpawn {
+        name: "Joe",
+        position: (200., 0.),
+        condition: None,
+    },
+    NpcSpawn {
+        name: "Rue",
+        position: (-200., 100.),
+        condition: None,
+    },
+    NpcSpawn {
+        name: "Moe",
+        position: (-350., 100.),
+        condition: Some("!moe_arrested"),
+    },
+];
@@ -0,0 +1,210 @@
+// When objects count as being next to the player, and which of them gets interacted with.
+
+use bevy::math::Vec2;
+use std::time::Duration;
+
+// Distances at which an object counts as in proximity.
+// Leaving takes going further than entering does, so standing on the edge
+// doesn't make an object come and go every frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProximityRange {
+    pub enter: f32,
+    pub exit: f32,
+    // how long the player has to stay across a radius for the change to count
+    pub dwell: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProximityChange {
+    Entered,
+    Left,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProximityState {
+    inside: bool,
+    distance: f32,
+    // for how long the player has been across the radius that would flip `inside`
+    crossing: Option<Duration>,
+}
+
+impl ProximityState {
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    // away and not about to come close, nothing changes until the player does
+    pub fn is_idle(&self) -> bool {
+        !self.inside && self.crossing.is_none()
+    }
+
+    pub fn update(
+        &mut self,
+        distance: f32,
+        delta: Duration,
+        range: &ProximityRange,
+    ) -> Option<ProximityChange> {
+        self.distance = distance;
+        let crossing = if self.inside {
+            distance > range.exit
+        } else {
+            distance < range.enter
+        };
+        if !crossing {
+            self.crossing = None;
+            return None;
+        }
+
+        let elapsed = match self.crossing {
+            Some(elapsed) => elapsed + delta,
+            None => Duration::ZERO,
+        };
+        if elapsed < range.dwell {
+            self.crossing = Some(elapsed);
+            return None;
+        }
+
+        self.crossing = None;
+        self.inside = !self.inside;
+        Some(if self.inside {
+            ProximityChange::Entered
+        } else {
+            ProximityChange::Left
+        })
+    }
+}
+
+// Whether `to_target` is within `half_angle` radians of `facing`.
+// Standing right on the target counts as facing it.
+pub fn in_cone(facing: Vec2, to_target: Vec2, half_angle: f32) -> bool {
+    if to_target == Vec2::ZERO || facing == Vec2::ZERO {
+        return true;
+    }
+    facing.angle_between(to_target).abs() <= half_angle
+}
+
+// Orders objects in range nearest first.
+// Equally distant objects keep their order in `previous`, the ones new to the ranking
+// go after them ordered by key, so the target doesn't flip between equals.
+pub fn rank<K: Copy + Ord>(in_range: &[(K, f32)], previous: &[K]) -> Vec<K> {
+    let previous_position = |key: &K| {
+        previous
+            .iter()
+            .position(|other| other == key)
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut ranked = in_range.to_vec();
+    ranked.sort_by(|(a, a_distance), (b, b_distance)| {
+        a_distance
+            .total_cmp(b_distance)
+            .then_with(|| previous_position(a).cmp(&previous_position(b)))
+            .then_with(|| a.cmp(b))
+    });
+    ranked.into_iter().map(|(key, _)| key).collect()
+}
+
+// first one is the nearest
+pub fn nearest<K: Copy>(ranked: &[K]) -> Option<K> {
+    ranked.first().copied()
+}
+
+// whether the target changed between rankings
+pub fn target_changed<K: Copy + PartialEq>(previous: &[K], current: &[K]) -> bool {
+    nearest(previous) != nearest(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    // distances walked, one per frame, and the changes they caused
+    fn walk(range: &ProximityRange, distances: &[f32]) -> Vec<ProximityChange> {
+        let mut state = ProximityState::default();
+        distances
+            .iter()
+            .filter_map(|&distance| state.update(distance, FRAME, range))
+            .collect()
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        use ProximityChange::*;
+        let range = ProximityRange {
+            enter: 100.,
+            exit: 120.,
+            dwell: Duration::ZERO,
+        };
+
+        assert_eq!(walk(&range, &[130., 99., 101., 99., 119., 110.]), [Entered]);
+        assert_eq!(
+            walk(&range, &[99., 121., 101., 119., 99.]),
+            [Entered, Left, Entered]
+        );
+        // never been close, never left
+        assert_eq!(walk(&range, &[130., 150.]), []);
+    }
+
+    #[test]
+    fn test_dwell() {
+        use ProximityChange::*;
+        let range = ProximityRange {
+            enter: 100.,
+            exit: 100.,
+            dwell: FRAME * 3,
+        };
+
+        // in and out every frame never lasts long enough
+        assert_eq!(walk(&range, &[90., 110., 90., 110., 90., 110.]), []);
+        assert_eq!(walk(&range, &[90., 90., 90., 90.]), [Entered]);
+        assert_eq!(
+            walk(&range, &[90., 90., 90., 90., 110., 110., 110., 110.]),
+            [Entered, Left]
+        );
+    }
+
+    #[test]
+    fn test_in_cone() {
+        use std::f32::consts::FRAC_PI_4;
+        let right = Vec2::X;
+
+        assert!(in_cone(right, Vec2::new(10., 0.), FRAC_PI_4));
+        assert!(in_cone(right, Vec2::new(10., -9.), FRAC_PI_4));
+        assert!(!in_cone(right, Vec2::new(10., 11.), FRAC_PI_4));
+        // behind
+        assert!(!in_cone(right, Vec2::new(-10., 0.), FRAC_PI_4));
+        assert!(in_cone(right, Vec2::ZERO, FRAC_PI_4));
+    }
+
+    #[test]
+    fn test_rank_by_distance() {
+        let ranked = rank(&[(1, 120.), (2, 40.), (3, 80.)], &[]);
+        assert_eq!(ranked, [2, 3, 1]);
+        assert_eq!(nearest(&ranked), Some(2));
+        assert_eq!(nearest::<u32>(&[]), None);
+    }
+
+    #[test]
+    fn test_rank_ties_are_stable() {
+        // new ones by key
+        assert_eq!(rank(&[(3, 50.), (1, 50.), (2, 10.)], &[]), [2, 1, 3]);
+        // known ones keep their place
+        assert_eq!(rank(&[(3, 50.), (1, 50.)], &[3, 1]), [3, 1]);
+        assert_eq!(rank(&[(3, 50.), (1, 50.), (2, 50.)], &[3]), [3, 1, 2]);
+    }
+
+    #[test]
+    fn test_target_changed() {
+        assert!(!target_changed::<u32>(&[], &[]));
+        assert!(target_changed(&[], &[1]));
+        assert!(target_changed(&[1], &[]));
+        assert!(target_changed(&[1, 2], &[2, 1]));
+        assert!(!target_changed(&[1, 2], &[1, 3]));
+    }
+}
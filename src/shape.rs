@@ -0,0 +1,264 @@
+// Outlines of objects on the plane, and how far apart they are.
+//
+// Shapes don't rotate, rectangles are always aligned with the axes.
+
+use bevy::math::Vec2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Point,
+    Circle { radius: f32 },
+    Rect { half_size: Vec2 },
+    // convex with at least three vertices, relative to the center of the object, in either winding
+    Polygon { vertices: Vec<Vec2> },
+}
+
+impl Shape {
+    pub fn rect(size: Vec2) -> Self {
+        Shape::Rect {
+            half_size: size / 2.,
+        }
+    }
+
+    // farthest the shape reaches from its center
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Shape::Point => 0.,
+            Shape::Circle { radius } => *radius,
+            Shape::Rect { half_size } => half_size.length(),
+            Shape::Polygon { vertices } => vertices
+                .iter()
+                .map(|vertex| vertex.length())
+                .fold(0., f32::max),
+        }
+    }
+
+    // circles are points grown by their radius
+    fn core(&self, at: Vec2) -> (Core, f32) {
+        match self {
+            Shape::Point => (Core::Point(at), 0.),
+            Shape::Circle { radius } => (Core::Point(at), *radius),
+            Shape::Rect { half_size } => (
+                Core::Rect {
+                    min: at - *half_size,
+                    max: at + *half_size,
+                },
+                0.,
+            ),
+            Shape::Polygon { vertices } => (
+                Core::Polygon(vertices.iter().map(|vertex| at + *vertex).collect()),
+                0.,
+            ),
+        }
+    }
+}
+
+enum Core {
+    Point(Vec2),
+    Rect { min: Vec2, max: Vec2 },
+    Polygon(Vec<Vec2>),
+}
+
+impl Core {
+    fn vertices(&self) -> Vec<Vec2> {
+        match self {
+            Core::Point(point) => vec![*point],
+            Core::Rect { min, max } => {
+                vec![*min, Vec2::new(max.x, min.y), *max, Vec2::new(min.x, max.y)]
+            }
+            Core::Polygon(vertices) => vertices.clone(),
+        }
+    }
+}
+
+// Gap between two shapes placed at `a_at` and `b_at`, zero when they touch or overlap
+pub fn gap(a: &Shape, a_at: Vec2, b: &Shape, b_at: Vec2) -> f32 {
+    let (a_core, a_radius) = a.core(a_at);
+    let (b_core, b_radius) = b.core(b_at);
+    (core_distance(&a_core, &b_core) - a_radius - b_radius).max(0.)
+}
+
+// Whether the segment from `start` to `end` goes through the shape placed at `at`
+pub fn segment_hits(shape: &Shape, at: Vec2, start: Vec2, end: Vec2) -> bool {
+    match shape.core(at) {
+        (Core::Point(point), radius) => segment_distance(point, start, end) <= radius,
+        (core, _) => {
+            let vertices = core.vertices();
+            let segment = [start, end];
+            !separated_along_edges(&vertices, &segment)
+                && !separated_along_edges(&segment, &vertices)
+        }
+    }
+}
+
+fn core_distance(a: &Core, b: &Core) -> f32 {
+    match (a, b) {
+        (Core::Point(a), Core::Point(b)) => a.distance(*b),
+        (Core::Point(point), Core::Rect { min, max })
+        | (Core::Rect { min, max }, Core::Point(point)) => point.clamp(*min, *max).distance(*point),
+        (
+            Core::Rect {
+                min: a_min,
+                max: a_max,
+            },
+            Core::Rect {
+                min: b_min,
+                max: b_max,
+            },
+        ) => {
+            // separation along each axis, zero where the projections overlap
+            let gap = (*b_min - *a_max).max(*a_min - *b_max).max(Vec2::ZERO);
+            gap.length()
+        }
+        _ => polygon_distance(&a.vertices(), &b.vertices()),
+    }
+}
+
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0. {
+        return point.distance(start);
+    }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0., 1.);
+    point.distance(start + segment * t)
+}
+
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let next = vertices.iter().cycle().skip(1);
+    vertices.iter().copied().zip(next.copied())
+}
+
+// whether projections on some edge normal don't overlap
+fn separated_along_edges(a: &[Vec2], b: &[Vec2]) -> bool {
+    edges(a).any(|(start, end)| {
+        let normal = (end - start).perp();
+        if normal == Vec2::ZERO {
+            return false;
+        }
+        let project = |vertices: &[Vec2]| {
+            vertices
+                .iter()
+                .map(|vertex| vertex.dot(normal))
+                .fold((f32::MAX, f32::MIN), |(min, max), x| {
+                    (min.min(x), max.max(x))
+                })
+        };
+        let (a_min, a_max) = project(a);
+        let (b_min, b_max) = project(b);
+        a_max < b_min || b_max < a_min
+    })
+}
+
+// Convex polygons, or a single point against one. Apart, the closest points
+// are a vertex of one of them and an edge of the other.
+fn polygon_distance(a: &[Vec2], b: &[Vec2]) -> f32 {
+    // a point has no edges of its own, the ones of the polygon decide alone
+    if !separated_along_edges(a, b) && !separated_along_edges(b, a) {
+        return 0.;
+    }
+
+    let closest = |vertices: &[Vec2], polygon: &[Vec2]| {
+        vertices
+            .iter()
+            .flat_map(|&vertex| edges(polygon).map(move |(start, end)| (vertex, start, end)))
+            .map(|(vertex, start, end)| segment_distance(vertex, start, end))
+            .fold(f32::MAX, f32::min)
+    };
+    closest(a, b).min(closest(b, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_circles_and_rects() {
+        let circle = Shape::Circle { radius: 50. };
+        let square = Shape::rect(Vec2::new(100., 100.));
+
+        assert_close(
+            gap(&Shape::Point, Vec2::ZERO, &circle, Vec2::new(80., 0.)),
+            30.,
+        );
+        assert_close(gap(&circle, Vec2::ZERO, &circle, Vec2::new(0., 130.)), 30.);
+        assert_close(gap(&circle, Vec2::ZERO, &circle, Vec2::new(0., 60.)), 0.);
+
+        // to the side of the square, then off its corner
+        assert_close(gap(&circle, Vec2::new(130., 0.), &square, Vec2::ZERO), 30.);
+        assert_close(
+            gap(&circle, Vec2::new(100., 100.), &square, Vec2::ZERO),
+            Vec2::new(50., 50.).length() - 50.,
+        );
+        assert_close(gap(&square, Vec2::new(0., 180.), &square, Vec2::ZERO), 80.);
+        assert_close(gap(&square, Vec2::new(50., 50.), &square, Vec2::ZERO), 0.);
+    }
+
+    #[test]
+    fn test_polygons() {
+        let triangle = Shape::Polygon {
+            vertices: vec![
+                Vec2::new(-50., -50.),
+                Vec2::new(50., -50.),
+                Vec2::new(0., 50.),
+            ],
+        };
+        let circle = Shape::Circle { radius: 10. };
+
+        assert_close(
+            gap(&circle, Vec2::new(0., -80.), &triangle, Vec2::ZERO),
+            20.,
+        );
+        assert_close(gap(&circle, Vec2::ZERO, &triangle, Vec2::ZERO), 0.);
+        assert_close(
+            gap(&Shape::Point, Vec2::new(0., 10.), &triangle, Vec2::ZERO),
+            0.,
+        );
+        assert_close(
+            gap(
+                &triangle,
+                Vec2::new(0., 150.),
+                &Shape::rect(Vec2::splat(100.)),
+                Vec2::ZERO,
+            ),
+            50.,
+        );
+        assert_close(
+            gap(&triangle, Vec2::new(20., 20.), &triangle, Vec2::ZERO),
+            0.,
+        );
+        assert_close(triangle.bounding_radius(), Vec2::new(50., 50.).length());
+    }
+
+    #[test]
+    fn test_segment_hits() {
+        let wall = Shape::rect(Vec2::new(20., 200.));
+        let pillar = Shape::Circle { radius: 10. };
+        let (left, right) = (Vec2::new(-100., 0.), Vec2::new(100., 0.));
+
+        assert!(segment_hits(&wall, Vec2::ZERO, left, right));
+        assert!(!segment_hits(&wall, Vec2::new(0., 150.), left, right));
+        // ends right before it
+        assert!(!segment_hits(&wall, Vec2::new(120., 0.), left, right));
+        // diagonally past the corner
+        assert!(!segment_hits(
+            &wall,
+            Vec2::ZERO,
+            Vec2::new(-100., 130.),
+            Vec2::new(100., 90.)
+        ));
+
+        assert!(segment_hits(&pillar, Vec2::new(0., 5.), left, right));
+        assert!(!segment_hits(&pillar, Vec2::new(0., 15.), left, right));
+        assert!(!segment_hits(&Shape::Point, Vec2::new(0., 1.), left, right));
+    }
+}
@@ -0,0 +1,154 @@
+// Uniform grid over the plane, to find what's around a point without checking everything.
+
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<K>>,
+    positions: HashMap<K, Vec2>,
+}
+
+impl<K: Copy + Eq + Hash> SpatialGrid<K> {
+    // queries are cheapest with cells about as big as the radius they look within
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0., "cell size must be positive");
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            positions: HashMap::default(),
+        }
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    // moves the key if it's already there
+    pub fn insert(&mut self, key: K, position: Vec2) {
+        let cell = self.cell(position);
+        if let Some(previous) = self.positions.insert(key, position) {
+            let previous = self.cell(previous);
+            if previous == cell {
+                return;
+            }
+            self.remove_from_cell(key, previous);
+        }
+        self.cells.entry(cell).or_default().push(key);
+    }
+
+    pub fn remove(&mut self, key: K) -> bool {
+        match self.positions.remove(&key) {
+            Some(position) => {
+                self.remove_from_cell(key, self.cell(position));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_from_cell(&mut self, key: K, cell: IVec2) {
+        if let Some(keys) = self.cells.get_mut(&cell) {
+            if let Some(idx) = keys.iter().position(|&other| other == key) {
+                keys.swap_remove(idx);
+            }
+            if keys.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    pub fn position(&self, key: K) -> Option<Vec2> {
+        self.positions.get(&key).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // everything within `radius` of `center`, along with its distance, in no particular order
+    pub fn within(&self, center: Vec2, radius: f32) -> Vec<(K, f32)> {
+        let min = self.cell(center - radius);
+        let max = self.cell(center + radius);
+
+        let mut found = vec![];
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let keys = match self.cells.get(&IVec2::new(x, y)) {
+                    Some(keys) => keys,
+                    None => continue,
+                };
+                for &key in keys {
+                    let distance = self.positions[&key].distance(center);
+                    if distance <= radius {
+                        found.push((key, distance));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut found: Vec<(u32, f32)>) -> Vec<u32> {
+        found.sort_by_key(|&(key, _)| key);
+        found.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn test_insert_move_remove() {
+        let mut grid = SpatialGrid::new(100.);
+        grid.insert(1, Vec2::new(10., 10.));
+        grid.insert(2, Vec2::new(-150., 40.));
+        grid.insert(3, Vec2::new(500., 500.));
+
+        assert_eq!(sorted(grid.within(Vec2::ZERO, 160.)), [1, 2]);
+
+        grid.insert(3, Vec2::new(-20., 0.));
+        grid.insert(1, Vec2::new(20., 10.));
+        assert_eq!(grid.len(), 3);
+        assert_eq!(grid.position(3), Some(Vec2::new(-20., 0.)));
+        assert_eq!(sorted(grid.within(Vec2::ZERO, 50.)), [1, 3]);
+
+        assert!(grid.remove(1));
+        assert!(!grid.remove(1));
+        assert_eq!(sorted(grid.within(Vec2::ZERO, 160.)), [2, 3]);
+        assert_eq!(grid.within(Vec2::new(500., 500.), 10.), []);
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        let rng = fastrand::Rng::with_seed(7);
+        let mut grid = SpatialGrid::new(64.);
+        let points: Vec<(u32, Vec2)> = (0..500)
+            .map(|key| {
+                let position = Vec2::new(rng.f32() * 2000. - 1000., rng.f32() * 2000. - 1000.);
+                (key, position)
+            })
+            .collect();
+        for &(key, position) in &points {
+            grid.insert(key, position);
+        }
+
+        for _ in 0..50 {
+            let center = Vec2::new(rng.f32() * 2000. - 1000., rng.f32() * 2000. - 1000.);
+            let radius = rng.f32() * 300.;
+            let expected: Vec<u32> = points
+                .iter()
+                .filter(|(_, position)| position.distance(center) <= radius)
+                .map(|&(key, _)| key)
+                .collect();
+            assert_eq!(sorted(grid.within(center, radius)), expected);
+        }
+    }
+}
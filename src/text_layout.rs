@@ -0,0 +1,133 @@
+// Fitting text into fixed size boxes: word wrapping and splitting into pages.
+
+use ab_glyph::{Font as _, FontArc, ScaleFont as _};
+
+// Measures text the way it will be rendered, in logical pixels
+#[derive(Clone)]
+pub struct TextMeasure {
+    // None until the font is loaded, widths are estimated in the meantime
+    font: Option<FontArc>,
+    size: f32,
+}
+
+impl TextMeasure {
+    pub fn new(font: Option<FontArc>, size: f32) -> Self {
+        Self { font, size }
+    }
+
+    pub fn width(&self, text: &str) -> f32 {
+        let font = match &self.font {
+            Some(font) => font.as_scaled(self.size),
+            None => return text.chars().count() as f32 * self.size * 0.5,
+        };
+
+        let mut width = 0.;
+        let mut previous = None;
+        for c in text.chars() {
+            let glyph = font.glyph_id(c);
+            if let Some(previous) = previous {
+                width += font.kern(previous, glyph);
+            }
+            width += font.h_advance(glyph);
+            previous = Some(glyph);
+        }
+        width
+    }
+
+    pub fn line_height(&self) -> f32 {
+        match &self.font {
+            Some(font) => {
+                let font = font.as_scaled(self.size);
+                font.height() + font.line_gap()
+            }
+            None => self.size * 1.2,
+        }
+    }
+}
+
+// Breaks text into lines no wider than `max_width`, on word boundaries where possible.
+// Explicit line breaks are kept, words wider than a line are split.
+pub fn wrap(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = vec![];
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_owned()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if width(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            let mut rest = word;
+            while width(rest) > max_width {
+                // at least a single character per line, otherwise it never ends
+                let split = rest
+                    .char_indices()
+                    .skip(1)
+                    .map(|(i, _)| i)
+                    .take_while(|&i| width(&rest[..i]) <= max_width)
+                    .last()
+                    .unwrap_or_else(|| rest.chars().next().map_or(0, char::len_utf8));
+                lines.push(rest[..split].to_owned());
+                rest = &rest[split..];
+            }
+            line = rest.to_owned();
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Groups lines into pages of at most `lines_per_page` lines
+pub fn paginate(lines: &[String], lines_per_page: usize) -> Vec<String> {
+    if lines.is_empty() {
+        return vec![String::new()];
+    }
+    lines
+        .chunks(lines_per_page.max(1))
+        .map(|page| page.join("\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every character is 10 pixels wide
+    fn width(text: &str) -> f32 {
+        text.chars().count() as f32 * 10.
+    }
+
+    #[test]
+    fn test_wrap_on_word_boundaries() {
+        let lines = wrap("Evening, detective. Rough night?", 100., width);
+        assert_eq!(lines, ["Evening,", "detective.", "Rough", "night?"]);
+
+        let lines = wrap("Only Moe. He was\ncarrying something", 120., width);
+        assert_eq!(lines, ["Only Moe. He", "was", "carrying", "something"]);
+    }
+
+    #[test]
+    fn test_wrap_splits_long_words() {
+        let lines = wrap("a Supercalifragilistic b", 50., width);
+        assert_eq!(lines, ["a", "Super", "calif", "ragil", "istic", "b"]);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let lines = wrap("one two three four five", 50., width);
+
+        assert_eq!(paginate(&lines, 2), ["one\ntwo", "three\nfour", "five"]);
+        assert_eq!(paginate(&lines, 0).len(), 5);
+        assert_eq!(paginate(&[], 3), [""]);
+    }
+}
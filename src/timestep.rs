@@ -0,0 +1,109 @@
+// Gameplay moves on in ticks of the same length, however long frames take,
+// so that it plays out the same at any frame rate.
+//
+// Frames add up the time that passed and gameplay catches up with it a tick at a time.
+// What's left, less than a tick, is how far along between the last two ticks
+// things get drawn.
+
+use bevy::prelude::Resource;
+use std::time::Duration;
+
+#[derive(Resource, Debug, Clone)]
+pub struct Timestep {
+    step: Duration,
+    accumulated: Duration,
+    // after a long hitch, rather than freezing to catch up, gameplay drops the rest
+    max_ticks: u32,
+}
+
+impl Default for Timestep {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1) / 60)
+    }
+}
+
+impl Timestep {
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "step must be positive");
+        Self {
+            step,
+            accumulated: Duration::ZERO,
+            max_ticks: 5,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    // at the start of every frame
+    pub fn advance(&mut self, delta: Duration) {
+        self.accumulated = (self.accumulated + delta).min(self.step * self.max_ticks);
+    }
+
+    // whether there's another tick to run this frame
+    pub fn tick(&mut self) -> bool {
+        if self.accumulated < self.step {
+            return false;
+        }
+        self.accumulated -= self.step;
+        true
+    }
+
+    // how far along the next tick the frame is, from 0 to 1
+    pub fn overstep(&self) -> f32 {
+        self.accumulated.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    // ticks run in each frame
+    fn ticks(timestep: &mut Timestep, frames: &[Duration]) -> Vec<u32> {
+        frames
+            .iter()
+            .map(|&frame| {
+                timestep.advance(frame);
+                let mut ticks = 0;
+                while timestep.tick() {
+                    ticks += 1;
+                }
+                ticks
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frame_pacing() {
+        let ms = Duration::from_millis;
+        let mut timestep = Timestep::new(STEP);
+        assert_eq!(ticks(&mut timestep, &[ms(10), ms(10), ms(10)]), [1, 1, 1]);
+        // faster frames skip ticks, slower ones catch up
+        assert_eq!(ticks(&mut timestep, &[ms(4), ms(4), ms(4)]), [0, 0, 1]);
+        assert!((timestep.overstep() - 0.2).abs() < 1e-4);
+        assert_eq!(ticks(&mut timestep, &[ms(28)]), [3]);
+        assert_eq!(timestep.overstep(), 0.);
+
+        // the same time passed the same ticks, however it's split up
+        let mut even = Timestep::new(STEP);
+        let mut uneven = Timestep::new(STEP);
+        let even: u32 = ticks(&mut even, &[ms(16); 10]).iter().sum();
+        let uneven: u32 = ticks(&mut uneven, &[ms(1), ms(33), ms(39), ms(7), ms(40), ms(40)])
+            .iter()
+            .sum();
+        assert_eq!(even, 16);
+        assert_eq!(even, uneven);
+    }
+
+    #[test]
+    fn test_catching_up_is_limited() {
+        let mut timestep = Timestep::new(STEP);
+        assert_eq!(ticks(&mut timestep, &[Duration::from_secs(3)]), [5]);
+        // the rest is gone
+        assert_eq!(ticks(&mut timestep, &[Duration::ZERO]), [0]);
+    }
+}
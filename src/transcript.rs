@@ -0,0 +1,95 @@
+// Everything said in dialogs so far, for the player to re-read.
+
+use bevy::prelude::Resource;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptEntry {
+    // whose conversation it was said in
+    pub npc: String,
+    pub speaker: String,
+    pub text: String,
+    // play time when the line was shown
+    pub at: Duration,
+}
+
+impl TranscriptEntry {
+    pub fn timestamp(&self) -> String {
+        let seconds = self.at.as_secs();
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Transcript {
+    entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    pub fn push(&mut self, entry: TranscriptEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // NPCs talked to, in the order they were first talked to
+    pub fn npcs(&self) -> Vec<&str> {
+        let mut npcs: Vec<&str> = vec![];
+        for entry in &self.entries {
+            if !npcs.contains(&entry.npc.as_str()) {
+                npcs.push(&entry.npc);
+            }
+        }
+        npcs
+    }
+
+    // oldest first, all of them unless filtered by NPC
+    pub fn entries<'a>(
+        &'a self,
+        npc: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a TranscriptEntry> + 'a {
+        self.entries.iter().filter(move |entry| match npc {
+            Some(npc) => entry.npc == npc,
+            None => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(npc: &str, speaker: &str, text: &str, at: u64) -> TranscriptEntry {
+        TranscriptEntry {
+            npc: npc.into(),
+            speaker: speaker.into(),
+            text: text.into(),
+            at: Duration::from_secs(at),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_npc() {
+        let mut transcript = Transcript::default();
+        transcript.push(entry("Joe", "Joe", "Evening, detective.", 5));
+        transcript.push(entry("Rue", "Rue", "You again?", 65));
+        transcript.push(entry("Joe", "Rue", "He was with me.", 125));
+
+        assert_eq!(transcript.npcs(), ["Joe", "Rue"]);
+        assert_eq!(transcript.entries(None).count(), 3);
+
+        let joe: Vec<_> = transcript.entries(Some("Joe")).map(|e| e.at).collect();
+        assert_eq!(joe, [Duration::from_secs(5), Duration::from_secs(125)]);
+        assert_eq!(transcript.entries(Some("Moe")).count(), 0);
+        assert_eq!(
+            transcript.entries(Some("Joe")).last().unwrap().timestamp(),
+            "02:05"
+        );
+    }
+}
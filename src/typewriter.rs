@@ -0,0 +1,125 @@
+// Reveals a line of text a character at a time.
+//
+// Time is fed in by the caller, so whatever drives it (game time, tests)
+// decides whether the reveal runs or stays frozen.
+
+use bevy::prelude::Resource;
+
+#[derive(Resource, Debug, Clone)]
+pub struct TypewriterSettings {
+    pub chars_per_second: f32,
+    // extra delay after a character ending a sentence, half of it after a comma and alike
+    pub punctuation_pause: f32,
+}
+
+impl Default for TypewriterSettings {
+    fn default() -> Self {
+        Self {
+            chars_per_second: 40.,
+            punctuation_pause: 0.3,
+        }
+    }
+}
+
+impl TypewriterSettings {
+    fn pause_after(&self, c: char) -> f32 {
+        match c {
+            '.' | '!' | '?' | '…' => self.punctuation_pause,
+            ',' | ';' | ':' | '—' => self.punctuation_pause / 2.,
+            _ => 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typewriter {
+    chars: Vec<char>,
+    revealed: usize,
+    // time accumulated but not yet spent on revealing characters
+    budget: f32,
+}
+
+impl Typewriter {
+    pub fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            revealed: 0,
+            budget: 0.,
+        }
+    }
+
+    pub fn visible(&self) -> String {
+        self.chars[..self.revealed].iter().collect()
+    }
+
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.revealed == self.chars.len()
+    }
+
+    pub fn finish(&mut self) {
+        self.revealed = self.chars.len();
+    }
+
+    // returns whether any character got revealed
+    pub fn tick(&mut self, delta_seconds: f32, settings: &TypewriterSettings) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        let before = self.revealed;
+        self.budget += delta_seconds;
+        while !self.is_finished() {
+            let mut cost = 1. / settings.chars_per_second;
+            if let Some(&previous) = self.revealed.checked_sub(1).map(|i| &self.chars[i]) {
+                cost += settings.pause_after(previous);
+            }
+            if self.budget < cost {
+                break;
+            }
+            self.budget -= cost;
+            self.revealed += 1;
+        }
+        self.revealed != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reveal_pauses_on_punctuation() {
+        let settings = TypewriterSettings {
+            chars_per_second: 10.,
+            punctuation_pause: 0.5,
+        };
+        let mut typewriter = Typewriter::new("Hi. Joe");
+
+        assert!(!typewriter.tick(0.05, &settings));
+        assert!(typewriter.tick(0.3, &settings));
+        assert_eq!(typewriter.visible(), "Hi.");
+
+        // the space right after the period waits for the pause
+        assert!(!typewriter.tick(0.5, &settings));
+        assert!(typewriter.tick(0.1, &settings));
+        assert_eq!(typewriter.visible(), "Hi. ");
+
+        typewriter.tick(10., &settings);
+        assert!(typewriter.is_finished());
+        assert_eq!(typewriter.visible(), "Hi. Joe");
+    }
+
+    #[test]
+    fn test_finish_skips_to_end() {
+        let mut typewriter = Typewriter::new("Evening, detective.");
+        typewriter.finish();
+
+        assert!(typewriter.is_finished());
+        assert_eq!(typewriter.visible(), "Evening, detective.");
+        assert!(!typewriter.tick(1., &TypewriterSettings::default()));
+    }
+}
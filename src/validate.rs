@@ -0,0 +1,362 @@
+// Static checks of dialog content, so that mistakes show up before anyone plays through.
+
+use crate::dialog::{DialogGraph, DialogNode, NodeId};
+use crate::flags::{Condition, Effect, GameFlags};
+use crate::npcs::NpcSpawn;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    // the NPC whose dialog or spawn has the problem
+    pub npc: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.npc, self.message)
+    }
+}
+
+struct Issues<'a> {
+    npc: &'a str,
+    found: Vec<Issue>,
+}
+
+impl<'a> Issues<'a> {
+    fn new(npc: &'a str) -> Self {
+        Self { npc, found: vec![] }
+    }
+
+    fn push(&mut self, severity: Severity, message: String) {
+        self.found.push(Issue {
+            severity,
+            npc: self.npc.into(),
+            message,
+        });
+    }
+
+    fn error(&mut self, message: String) {
+        self.push(Severity::Error, message);
+    }
+
+    fn warning(&mut self, message: String) {
+        self.push(Severity::Warning, message);
+    }
+
+    // every flag must be declared, and the condition must hold a bool given flags of these types
+    fn condition(&mut self, condition: &Condition, flags: &GameFlags, context: &str) {
+        let mut undefined = false;
+        for flag in condition.flags() {
+            if !flags.is_declared(flag) {
+                undefined = true;
+                self.error(format!("{} reads undefined flag `{}`", context, flag));
+            }
+        }
+        if !undefined {
+            if let Err(e) = condition.eval(flags) {
+                self.error(format!("{}: {}", context, e));
+            }
+        }
+    }
+
+    fn effects(&mut self, effects: &[Effect], flags: &GameFlags, context: &str) {
+        for effect in effects {
+            let mut undefined = false;
+            for flag in effect.flags() {
+                if !flags.is_declared(flag) {
+                    undefined = true;
+                    self.error(format!(
+                        "{} effect `{}` uses undefined flag `{}`",
+                        context,
+                        effect.source(),
+                        flag
+                    ));
+                }
+            }
+            // applied to a copy, only to find type mismatches
+            if !undefined {
+                if let Err(e) = effect.apply(&mut flags.clone()) {
+                    self.error(format!("{}: {}", context, e));
+                }
+            }
+        }
+    }
+
+    fn target(&mut self, graph: &DialogGraph, target: &Option<NodeId>, context: &str) {
+        if let Some(target) = target {
+            if graph.node(target).is_none() {
+                self.error(format!("{} leads to missing node `{}`", context, target));
+            }
+        }
+    }
+}
+
+// nodes a node may lead to, missing ones included
+fn successors(node: &DialogNode) -> impl Iterator<Item = &NodeId> {
+    node.next.iter().chain(
+        node.choices
+            .iter()
+            .filter_map(|choice| choice.next.as_ref()),
+    )
+}
+
+// whether the conversation may end right at the node
+fn may_end_at(graph: &DialogGraph, node: &DialogNode) -> bool {
+    let ends_without_choice = node.next.is_none()
+        && (node.choices.is_empty()
+            // with none of the conditions holding there's nothing to pick
+            || node.choices.iter().all(|choice| choice.condition.is_some()));
+    ends_without_choice
+        || node.choices.iter().any(|choice| choice.next.is_none())
+        // reported as a dangling target, no need to report it twice
+        || successors(node).any(|next| graph.node(next).is_none())
+}
+
+pub fn validate_graph(graph: &DialogGraph, flags: &GameFlags) -> Vec<Issue> {
+    let mut issues = Issues::new(&graph.npc);
+
+    let mut roots = vec![&graph.start];
+    if graph.node(&graph.start).is_none() {
+        issues.error(format!("start node `{}` does not exist", graph.start));
+    }
+    if let Some(seconds) = graph.bark_cooldown {
+        if !(seconds.is_finite() && seconds >= 0.) {
+            issues.error(format!("bark cooldown of {} seconds", seconds));
+        }
+    }
+    for (idx, entry) in graph.entries.iter().enumerate() {
+        let context = format!("entry #{}", idx + 1);
+        issues.condition(&entry.condition, flags, &context);
+        issues.target(graph, &Some(entry.node.clone()), &context);
+        roots.push(&entry.node);
+    }
+
+    for (id, node) in &graph.nodes {
+        let context = format!("node `{}`", id);
+        issues.effects(&node.effects, flags, &context);
+        issues.target(graph, &node.next, &context);
+        for (idx, choice) in node.choices.iter().enumerate() {
+            let context = format!("choice #{} of node `{}`", idx + 1, id);
+            if let Some(condition) = &choice.condition {
+                issues.condition(condition, flags, &context);
+            }
+            issues.effects(&choice.effects, flags, &context);
+            issues.target(graph, &choice.next, &context);
+        }
+    }
+
+    // breadth first from where conversations may start
+    let mut reachable = BTreeSet::new();
+    let mut queue: VecDeque<&NodeId> = roots.into_iter().collect();
+    while let Some(id) = queue.pop_front() {
+        let node = match graph.node(id) {
+            Some(node) => node,
+            None => continue,
+        };
+        if reachable.insert(id) {
+            queue.extend(successors(node));
+        }
+    }
+    for id in graph.nodes.keys() {
+        if !reachable.contains(id) {
+            issues.error(format!("node `{}` is unreachable", id));
+        }
+    }
+
+    // nodes that lead to an end, grown until nothing changes
+    let mut ending: BTreeSet<&NodeId> = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| may_end_at(graph, node))
+        .map(|(id, _)| id)
+        .collect();
+    loop {
+        let before = ending.len();
+        for (id, node) in &graph.nodes {
+            if successors(node).any(|next| ending.contains(next)) {
+                ending.insert(id);
+            }
+        }
+        if ending.len() == before {
+            break;
+        }
+    }
+    for id in graph.nodes.keys() {
+        if !ending.contains(id) {
+            issues.error(format!("conversation never ends once at node `{}`", id));
+        }
+    }
+
+    issues.found
+}
+
+// NPCs of the level against the dialogs written for them
+pub fn validate_npcs<'a>(
+    npcs: &[NpcSpawn],
+    graphs: impl IntoIterator<Item = &'a DialogGraph>,
+    flags: &GameFlags,
+) -> Vec<Issue> {
+    let graphs: Vec<_> = graphs.into_iter().collect();
+    let mut found = vec![];
+
+    for npc in npcs {
+        let mut issues = Issues::new(npc.name);
+        if !graphs.iter().any(|graph| graph.npc == npc.name) {
+            issues.error("no dialog, a placeholder line is shown instead".into());
+        }
+        if let Some(condition) = npc.condition {
+            match Condition::parse(condition) {
+                Ok(condition) => issues.condition(&condition, flags, "spawn condition"),
+                Err(e) => issues.error(format!("spawn condition: {}", e)),
+            }
+        }
+        found.extend(issues.found);
+    }
+
+    for graph in graphs {
+        if !npcs.iter().any(|npc| npc.name == graph.npc) {
+            let mut issues = Issues::new(&graph.npc);
+            issues.warning("dialog for an NPC that is not in the level".into());
+            found.extend(issues.found);
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialog::{parse_graph, DialogFormat, DialogLibrary};
+    use crate::flags::FlagValue;
+    use crate::npcs::NPCS;
+    use std::path::Path;
+
+    fn flags() -> GameFlags {
+        let mut flags = GameFlags::default();
+        flags.declare("met_joe", FlagValue::Bool(false));
+        flags.declare("clues", FlagValue::Int(0));
+        flags
+    }
+
+    fn messages(issues: &[Issue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_graph() {
+        let graph = parse_graph(
+            r#"(
+                npc: "Joe",
+                start: "greeting",
+                entries: [(condition: "met_joe", node: "again")],
+                nodes: {
+                    "greeting": (
+                        text: "Evening.",
+                        effects: ["met_joe = true"],
+                        choices: [(text: "Clues?", condition: "clues > 0", next: "again")],
+                    ),
+                    "again": (text: "You again?", next: "loop"),
+                    "loop": (text: "Anything else?", choices: [(text: "Again.", next: "again"), (text: "No.")]),
+                },
+            )"#,
+            DialogFormat::Ron,
+        )
+        .unwrap();
+
+        assert_eq!(validate_graph(&graph, &flags()), []);
+    }
+
+    #[test]
+    fn test_graph_problems() {
+        let graph = parse_graph(
+            r#"(
+                npc: "Joe",
+                start: "greeting",
+                bark_cooldown: inf,
+                nodes: {
+                    "greeting": (
+                        text: "Evening.",
+                        choices: [
+                            (text: "Where?", next: "nowhere"),
+                            (text: "Ask", condition: "asked || clues", next: "stuck"),
+                        ],
+                    ),
+                    "stuck": (text: "Hm.", next: "stuck_too", effects: ["clues += true"]),
+                    "stuck_too": (text: "Hm?", choices: [(text: "Hm.", next: "stuck")]),
+                    "orphan": (text: "Nobody hears me."),
+                },
+            )"#,
+            DialogFormat::Ron,
+        )
+        .unwrap();
+        let issues = validate_graph(&graph, &flags());
+
+        assert!(issues.iter().all(|issue| issue.severity == Severity::Error));
+        assert_eq!(
+            messages(&issues),
+            [
+                "bark cooldown of inf seconds",
+                "choice #1 of node `greeting` leads to missing node `nowhere`",
+                "choice #2 of node `greeting` reads undefined flag `asked`",
+                "node `stuck`: clues += true expects int, got bool",
+                "node `orphan` is unreachable",
+                "conversation never ends once at node `stuck`",
+                "conversation never ends once at node `stuck_too`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_npcs_without_dialog() {
+        let npcs = [
+            NpcSpawn {
+                name: "Joe",
+                position: (0., 0.),
+                condition: Some("!arrested"),
+            },
+            NpcSpawn {
+                name: "Rue",
+                position: (0., 0.),
+                condition: None,
+            },
+        ];
+        let graphs = [DialogGraph::fallback("Joe"), DialogGraph::fallback("Moe")];
+        let issues = validate_npcs(&npcs, &graphs, &flags());
+
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "error: Joe: spawn condition reads undefined flag `arrested`",
+                "error: Rue: no dialog, a placeholder line is shown instead",
+                "warning: Moe: dialog for an NPC that is not in the level",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shipped_content_is_valid() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let library = DialogLibrary::load_dir(assets.join("dialogs")).unwrap();
+        let flags = GameFlags::load(assets.join("flags.ron")).unwrap();
+
+        let mut issues = validate_npcs(NPCS, library.iter(), &flags);
+        for graph in library.iter() {
+            issues.extend(validate_graph(graph, &flags));
+        }
+        assert_eq!(issues, []);
+    }
+}